@@ -10,6 +10,10 @@ pub enum BPImage {
     Armor,
     Grapple,
     Gun,
+    /// the rest share art with the above, but have more gem slots
+    Greatsword,
+    Plate,
+    Cannon,
 }
 
 impl BPImage {
@@ -79,5 +83,35 @@ pub fn load_blueprints() {
             },
         );
     }
+    {
+        let xp = XpFile::from_resource("../assets/sword.xp").unwrap();
+        map.insert(
+            BPImage::Greatsword,
+            BPIData {
+                img: xp,
+                gem_spots: vec![Point::new(8, 19), Point::new(8, 13)],
+            },
+        );
+    }
+    {
+        let xp = XpFile::from_resource("../assets/armor.xp").unwrap();
+        map.insert(
+            BPImage::Plate,
+            BPIData {
+                img: xp,
+                gem_spots: vec![Point::new(8, 15), Point::new(5, 12), Point::new(11, 12)],
+            },
+        );
+    }
+    {
+        let xp = XpFile::from_resource("../assets/elemental-gun.xp").unwrap();
+        map.insert(
+            BPImage::Cannon,
+            BPIData {
+                img: xp,
+                gem_spots: vec![Point::new(3, 15), Point::new(10, 6)],
+            },
+        );
+    }
     *BLUEPRINTS.lock().unwrap() = map;
 }
@@ -0,0 +1,282 @@
+//! The Incarnations waiting on the last floor. On top of whatever skills they have, each winds up a special attack
+//! of its element: the tiles it will hit are marked on the map for a turn before it lands, so the player can get
+//! out of the way. As a boss gets hurt it moves through the phases in its `Boss` component, each hitting harder and
+//! more often, and turning more of the arena into its own element's ground.
+
+use bracket_lib::prelude::*;
+use hecs::{Entity, Without};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::{post, Event, HitKind},
+    components::*,
+    damage::Damage,
+    equipment::element_color,
+    map::{Direction, Tile},
+    math::normalize_pt,
+    scheduler::ACTION_COST,
+    skill::{Shot, Skill},
+    status::{apply_status, StatusEffect, StatusKind},
+    State,
+};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BossAttack {
+    /// burns everything around it
+    Nova,
+    /// knocks everything around it away
+    TidalPush,
+    /// drags everything around it in close
+    CyclonePull,
+}
+
+impl BossAttack {
+    pub fn element(self) -> Elemental {
+        match self {
+            BossAttack::Nova => Elemental::Fire,
+            BossAttack::TidalPush => Elemental::Water,
+            BossAttack::CyclonePull => Elemental::Air,
+        }
+    }
+    fn warning(self) -> &'static str {
+        match self {
+            BossAttack::Nova => "glows white hot!",
+            BossAttack::TidalPush => "draws the water back!",
+            BossAttack::CyclonePull => "starts to spin!",
+        }
+    }
+    /// what the player is told when it lands on them
+    pub fn hit(self) -> &'static str {
+        match self {
+            BossAttack::Nova => "The blast burns you",
+            BossAttack::TidalPush => "The wave knocks you back",
+            BossAttack::CyclonePull => "The cyclone drags you in",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct BossPhase {
+    /// starts once health is at or under this percentage of the most it can have
+    pub below: i32,
+    /// announced when it starts
+    #[serde(default)]
+    pub message: String,
+    /// how far from the boss its attack reaches
+    pub radius: i32,
+    pub damage: i32,
+    /// rounds from one attack to the next
+    pub every: u32,
+    /// how many tiles of the arena turn into its element's ground when this phase starts
+    #[serde(default)]
+    pub hazards: usize,
+    /// what its `Skilled` skills become, if anything
+    #[serde(default)]
+    pub skills: Vec<(f32, Skill)>,
+}
+
+/// Which of `phases` a boss with `health` should be in, given they go from the most health to the least
+pub fn phase_for(phases: &[BossPhase], health: &Health) -> usize {
+    let percent = health.hp * 100 / health.max_hp.max(1);
+    phases.iter().rposition(|p| percent <= p.below).unwrap_or(0)
+}
+
+/// Returns the energy spent if the boss did something only a boss does, otherwise it acts like any other monster
+pub fn boss_act(state: &mut State, entity: Entity, sees_player: bool) -> Option<i32> {
+    let (health, boss) = state.ecs.query_one_mut::<(&Health, &Boss)>(entity).ok()?;
+    let (from, to) = (boss.phase, phase_for(&boss.phases, health));
+    for phase in from + 1..=to {
+        enter_phase(state, entity, phase);
+    }
+
+    let (boss, pos, name) = state
+        .ecs
+        .query_one_mut::<(&Boss, &Position, &Name)>(entity)
+        .ok()?;
+    if !boss.telegraph.is_empty() {
+        unleash(state, entity);
+        return Some(ACTION_COST);
+    }
+    if boss.recharge > 0 || !sees_player {
+        return None;
+    }
+    let (pos, name, attack) = (pos.0, name.0.clone(), boss.attack);
+    let phase = boss.phases[boss.phase].clone();
+    let player_pos = state
+        .ecs
+        .query_one_mut::<&Position>(state.player_entity)
+        .unwrap()
+        .0;
+    if DistanceAlg::Pythagoras.distance2d(pos, player_pos) > phase.radius as f32 {
+        return None;
+    }
+    let area: Vec<Point> = field_of_view(pos, phase.radius, &state.map)
+        .into_iter()
+        .filter(|pt| {
+            *pt != pos
+                && state.map.in_bounds(*pt)
+                && state.map.tiles[state.map.point2d_to_index(*pt)].walkable()
+                && DistanceAlg::Pythagoras.distance2d(pos, *pt) <= phase.radius as f32
+        })
+        .collect();
+    let boss = state.ecs.query_one_mut::<&mut Boss>(entity).unwrap();
+    boss.telegraph = area;
+    boss.recharge = phase.every;
+    state
+        .messages
+        .enqueue_message(&format!("The {} {}", name, attack.warning()));
+    Some(ACTION_COST)
+}
+
+fn enter_phase(state: &mut State, entity: Entity, idx: usize) {
+    let Ok((boss, pos)) = state.ecs.query_one_mut::<(&mut Boss, &Position)>(entity) else { return };
+    boss.phase = idx;
+    let phase = boss.phases[idx].clone();
+    let (pos, element) = (pos.0, boss.attack.element());
+    if !phase.message.is_empty() {
+        state.messages.enqueue_message(&phase.message);
+    }
+    if !phase.skills.is_empty() {
+        let skilled = Skilled {
+            skills: phase.skills,
+            cooldowns: vec![],
+        };
+        state.ecs.insert_one(entity, skilled).unwrap();
+    }
+
+    // the arena around it gives way to its element
+    let mut floor: Vec<Point> = field_of_view(pos, 8, &state.map)
+        .into_iter()
+        .filter(|pt| {
+            let idx = state.map.point2d_to_index(*pt);
+            state.map.in_bounds(*pt)
+                && state.map.tiles[idx] == Tile::Floor
+                && state.map.tile_contents[idx].is_empty()
+        })
+        .collect();
+    for _ in 0..phase.hazards.min(floor.len()) {
+        let pt = floor.swap_remove(state.rng.range(0, floor.len()));
+        let idx = state.map.point2d_to_index(pt);
+        let tile = match element {
+            Elemental::Fire => Tile::Lava,
+            Elemental::Water => Tile::DeepWater,
+            Elemental::Air => Tile::Wind(toward(pt, pos)),
+        };
+        state.map.set_tile(idx, tile);
+    }
+}
+
+/// The way from `from` that heads most directly to `to`
+fn toward(from: Point, to: Point) -> Direction {
+    let d = to - from;
+    if d.x.abs() > d.y.abs() {
+        if d.x > 0 {
+            Direction::East
+        } else {
+            Direction::West
+        }
+    } else if d.y > 0 {
+        Direction::South
+    } else {
+        Direction::North
+    }
+}
+
+/// Lands the attack on whoever is still on the marked tiles
+fn unleash(state: &mut State, entity: Entity) {
+    let (boss, pos) = state
+        .ecs
+        .query_one_mut::<(&mut Boss, &Position)>(entity)
+        .unwrap();
+    let area = std::mem::take(&mut boss.telegraph);
+    let (pos, attack) = (pos.0, boss.attack);
+    let damage = boss.phases[boss.phase].damage;
+    let radius = boss.phases[boss.phase].radius;
+
+    // it doesn't catch itself or the monsters fighting alongside it
+    let mut hit = vec![];
+    for (other, (p, _)) in state
+        .ecs
+        .query_mut::<Without<(&Position, &Health), &Monster>>()
+    {
+        if area.contains(&p.0) {
+            hit.push((other, p.0));
+        }
+    }
+    let damage = Damage::of(damage, attack.element());
+    for (other, at) in hit {
+        post(
+            state,
+            Event::Hit {
+                source: Some(entity),
+                target: other,
+                damage: damage.clone(),
+                kind: HitKind::Blast(attack),
+            },
+        );
+        let away = normalize_pt(at - pos);
+        match attack {
+            BossAttack::Nova => apply_status(
+                &mut state.ecs,
+                other,
+                StatusEffect {
+                    kind: StatusKind::Burn,
+                    duration: 3,
+                    power: 1,
+                },
+            ),
+            // pushing needs something that can see where it ends up
+            BossAttack::TidalPush if state.ecs.satisfies::<&Viewer>(other).unwrap_or(false) => {
+                let dest = at + away * radius;
+                let dest = Point::new(
+                    dest.x.clamp(0, state.map.width - 1),
+                    dest.y.clamp(0, state.map.height - 1),
+                );
+                post(state, Event::Push { entity: other, dest });
+            }
+            BossAttack::CyclonePull if state.ecs.satisfies::<&Viewer>(other).unwrap_or(false) => {
+                post(
+                    state,
+                    Event::Push {
+                        entity: other,
+                        dest: pos + away,
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    state.shots.push(Shot {
+        tiles: area,
+        glyph: '*',
+        fg: element_color(&attack.element()),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(below: i32) -> BossPhase {
+        BossPhase {
+            below,
+            message: String::new(),
+            radius: 1,
+            damage: 1,
+            every: 1,
+            hazards: 0,
+            skills: vec![],
+        }
+    }
+
+    #[test]
+    fn phases_follow_health() {
+        let phases = vec![phase(100), phase(60), phase(25)];
+        let at = |hp| phase_for(&phases, &Health { hp, max_hp: 40 });
+        assert_eq!(at(40), 0);
+        assert_eq!(at(25), 0);
+        assert_eq!(at(24), 1);
+        assert_eq!(at(10), 2);
+        assert_eq!(at(-3), 2);
+    }
+}
@@ -0,0 +1,370 @@
+//! Everything that happens in a fight goes through here as an `Event`. Whoever causes something posts it, it gets
+//! resolved, and every subscriber hears about it, so nothing else needs to know who's listening. Some events ask
+//! for something to happen (`Attack`, `Hit`, `Push`) and resolving them posts the events saying what did; the rest
+//! just say what happened. Subscribers hear about each event before it's resolved, so a `Death` still has its
+//! entity around to be named.
+//!
+//! Events posted while others are being resolved wait their turn, and all of them are through by the time `post`
+//! returns.
+
+use std::collections::{HashMap, VecDeque};
+
+use bracket_lib::prelude::*;
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    boss::BossAttack,
+    components::*,
+    damage::{deal_damage, defence_of, Damage, Effectiveness},
+    equipment::{execute_attack_effects, execute_defence_effects},
+    essence::{gain_essence, Essence},
+    save,
+    skill::Shot,
+    util::push_entity_in_line_to,
+    OperatingMode, State,
+};
+
+/// What did the hurting, for saying so
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HitKind {
+    Melee,
+    Shot,
+    Breath,
+    Blast(BossAttack),
+    /// from equipment, which has messages of its own
+    Effect,
+    Burn,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// `attacker` swings at `target` with its `Attack`, which has to get past the target's `Defence`
+    Attack { attacker: Entity, target: Entity },
+    /// `target` is to take `damage`
+    Hit {
+        source: Option<Entity>,
+        target: Entity,
+        damage: Damage,
+        kind: HitKind,
+    },
+    /// `target` lost `amount` health
+    Damaged {
+        source: Option<Entity>,
+        target: Entity,
+        amount: i32,
+        effectiveness: Effectiveness,
+        kind: HitKind,
+    },
+    /// an attack didn't connect
+    Miss { attacker: Entity, target: Entity },
+    /// an attack connected, but `target` blocked it or its armor stopped all of it
+    Block { attacker: Entity, target: Entity },
+    /// `entity` is out of health, and `killer` dealt the last of the damage if anything did
+    Death {
+        entity: Entity,
+        killer: Option<Entity>,
+    },
+    /// something dead left essence for the player's artifact
+    EssenceDrop { essence: Essence },
+    /// `entity` is shoved in a line towards `dest`, as far as it'll go. It has to have a `Viewer`
+    Push { entity: Entity, dest: Point },
+}
+
+/// Events waiting to be resolved, never saved since they're all through by the end of a turn
+#[derive(Clone, Debug, Default)]
+pub struct Events {
+    queue: VecDeque<Event>,
+    resolving: bool,
+    /// whatever last hurt each entity, `None` for burns and the like
+    last_hit: HashMap<Entity, Option<Entity>>,
+}
+
+impl Events {
+    /// Who gets the credit if `entity` dies now
+    pub fn killer(&self, entity: Entity) -> Option<Entity> {
+        self.last_hit.get(&entity).copied().flatten()
+    }
+    /// Forgets who hit what, for when the floor everything was hit on is left behind
+    pub fn forget_hits(&mut self) {
+        self.last_hit.clear();
+    }
+}
+
+/// How the run has gone, shown when it ends
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Stats {
+    pub kills: u32,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub essence: u32,
+}
+
+/// Everything that hears about each event, in this order
+const SUBSCRIBERS: [fn(&mut State, &Event); 4] =
+    [announce, trigger_equipment, animate, record_stats];
+
+pub fn post(state: &mut State, event: Event) {
+    state.events.queue.push_back(event);
+    if state.events.resolving {
+        return;
+    }
+    state.events.resolving = true;
+    while let Some(event) = state.events.queue.pop_front() {
+        for subscriber in SUBSCRIBERS {
+            subscriber(state, &event);
+        }
+        resolve(state, &event);
+    }
+    state.events.resolving = false;
+}
+
+fn resolve(state: &mut State, event: &Event) {
+    match event {
+        Event::Attack { attacker, target } => {
+            let Ok(damage) = state.ecs.get::<&Attack>(*attacker).map(|a| a.damage) else { return };
+            // monsters hit with their element, if they have one
+            let element = state
+                .ecs
+                .get::<&Elemental>(*attacker)
+                .ok()
+                .map(|e| (*e).clone());
+            let defence = defence_of(&state.ecs, *target);
+            if chance(state, defence.evasion) {
+                post(
+                    state,
+                    Event::Miss {
+                        attacker: *attacker,
+                        target: *target,
+                    },
+                );
+                return;
+            }
+            // armor pieces go off on whatever reaches the player, blocked or not
+            if *target == state.player_entity {
+                execute_defence_effects(state, *attacker);
+            }
+            let amount = defence.reduce(damage);
+            // armor soaking up all of it is as good as a block
+            if amount == 0 || chance(state, defence.block) {
+                post(
+                    state,
+                    Event::Block {
+                        attacker: *attacker,
+                        target: *target,
+                    },
+                );
+                return;
+            }
+            let damage = Damage { amount, element };
+            post(
+                state,
+                Event::Hit {
+                    source: Some(*attacker),
+                    target: *target,
+                    damage,
+                    kind: HitKind::Melee,
+                },
+            );
+        }
+        Event::Hit {
+            source,
+            target,
+            damage,
+            kind,
+        } => {
+            if let Some((amount, effectiveness)) = deal_damage(&mut state.ecs, *target, damage) {
+                post(
+                    state,
+                    Event::Damaged {
+                        source: *source,
+                        target: *target,
+                        amount,
+                        effectiveness,
+                        kind: *kind,
+                    },
+                );
+            }
+        }
+        Event::Damaged {
+            source,
+            target,
+            amount,
+            ..
+        } => {
+            if *amount > 0 {
+                state.events.last_hit.insert(*target, *source);
+            }
+        }
+        Event::Death { entity, .. } => {
+            state.events.last_hit.remove(entity);
+            if *entity == state.player_entity {
+                state.operating_mode = OperatingMode::GameOver;
+                save::delete_save();
+                return;
+            }
+            let drop = state
+                .ecs
+                .query_one_mut::<(Option<&Elemental>, Option<&Rank>)>(*entity)
+                .ok()
+                .and_then(|(e, r)| Some((e?.clone(), r?.0)));
+            if let Some((element, power)) = drop {
+                // 1 in 3, and bosses have nothing that would fit
+                if state.rng.range(0, 3) == 0 && (0..=2).contains(&power) {
+                    post(
+                        state,
+                        Event::EssenceDrop {
+                            essence: Essence { element, power },
+                        },
+                    );
+                }
+            }
+            state.ecs.despawn(*entity).unwrap();
+        }
+        Event::EssenceDrop { essence } => gain_essence(state, essence.clone()),
+        Event::Push { entity, dest } => push_entity_in_line_to(state, *entity, *dest),
+        Event::Miss { .. } | Event::Block { .. } => {}
+    }
+}
+
+/// `percent` in 100, without touching the rng when there's no chance at all
+fn chance(state: &mut State, percent: i32) -> bool {
+    percent > 0 && state.rng.range(0, 100) < percent
+}
+
+fn name_of(state: &State, entity: Option<Entity>) -> String {
+    entity
+        .and_then(|e| state.ecs.get::<&Name>(e).ok().map(|n| n.0.clone()))
+        .unwrap_or_else(|| "something".to_string())
+}
+
+fn is_seen(state: &State, entity: Entity) -> bool {
+    state.ecs.get::<&Position>(entity).map_or(false, |p| {
+        state.map.visible_tiles[state.map.point2d_to_index(p.0)]
+    })
+}
+
+/// What the player gets told
+fn announce(state: &mut State, event: &Event) {
+    let player = state.player_entity;
+    let mut messages = vec![];
+    match event {
+        Event::Damaged {
+            source,
+            target,
+            amount,
+            effectiveness,
+            kind,
+        } => {
+            let by = name_of(state, *source);
+            match kind {
+                // shrugged off entirely, which the resist message says
+                _ if *amount == 0 => {}
+                HitKind::Melee if *source == Some(player) => messages.push(format!(
+                    "You hit the {} for {} damage.",
+                    name_of(state, Some(*target)),
+                    amount
+                )),
+                HitKind::Melee if *target == player => {
+                    messages.push(format!("The {} hits you for {} damage.", by, amount))
+                }
+                HitKind::Shot if *target == player => {
+                    messages.push(format!("The {} shoots you for {} damage.", by, amount))
+                }
+                HitKind::Breath if *target == player => {
+                    messages.push(format!("The {} breathes on you for {} damage.", by, amount))
+                }
+                HitKind::Blast(attack) if *target == player => {
+                    messages.push(format!("{} for {} damage.", attack.hit(), amount))
+                }
+                _ => {}
+            }
+            if *target == player || *source == Some(player) {
+                if let Some(message) = effectiveness.message(*target == player) {
+                    messages.push(message.to_string());
+                }
+            }
+        }
+        Event::Miss { attacker, target } => {
+            if *attacker == player {
+                messages.push(format!("You miss the {}.", name_of(state, Some(*target))));
+            } else if *target == player {
+                messages.push(format!(
+                    "The {} misses you.",
+                    name_of(state, Some(*attacker))
+                ));
+            }
+        }
+        Event::Block { attacker, target } => {
+            if *attacker == player {
+                messages.push(format!("The {} blocks you.", name_of(state, Some(*target))));
+            } else if *target == player {
+                messages.push(format!("You block the {}.", name_of(state, Some(*attacker))));
+            }
+        }
+        Event::Death { entity, .. } if *entity == player => {
+            messages.push("You are DEAD.".to_string())
+        }
+        Event::Death { entity, .. } if is_seen(state, *entity) => {
+            messages.push(format!("The {} dies.", name_of(state, Some(*entity))))
+        }
+        _ => {}
+    }
+    for message in messages {
+        state.messages.enqueue_message(&message);
+    }
+}
+
+/// The player's artifacts that go off when they hit something in melee
+fn trigger_equipment(state: &mut State, event: &Event) {
+    if let Event::Damaged {
+        source: Some(source),
+        target,
+        kind: HitKind::Melee,
+        ..
+    } = event
+    {
+        if *source == state.player_entity {
+            execute_attack_effects(state, *target);
+        }
+    }
+}
+
+/// Marks whoever got hurt until the player's next action
+fn animate(state: &mut State, event: &Event) {
+    let Event::Damaged { target, amount, .. } = event else { return };
+    if *amount <= 0 {
+        return;
+    }
+    let Ok(pos) = state.ecs.get::<&Position>(*target).map(|p| p.0) else { return };
+    state.shots.push(Shot {
+        tiles: vec![pos],
+        glyph: '*',
+        fg: RGB::named(RED),
+    });
+}
+
+fn record_stats(state: &mut State, event: &Event) {
+    let player = state.player_entity;
+    let stats = &mut state.stats;
+    match event {
+        Event::Damaged {
+            source,
+            target,
+            amount,
+            ..
+        } => {
+            if *source == Some(player) {
+                stats.damage_dealt += amount;
+            }
+            if *target == player {
+                stats.damage_taken += amount;
+            }
+        }
+        Event::Death { entity, killer } if *entity != player && *killer == Some(player) => {
+            stats.kills += 1
+        }
+        Event::EssenceDrop { .. } => stats.essence += 1,
+        _ => {}
+    }
+}
@@ -1,12 +1,17 @@
+use std::collections::HashMap;
+
 use bracket_lib::prelude::*;
 use hecs::Entity;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     blueprint::BPImage,
+    boss::{BossAttack, BossPhase},
     equipment::{Equipment, EquipmentType},
     essence::Essence,
+    scheduler::ACTION_COST,
     skill::Skill,
+    status::StatusEffect,
 };
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -41,8 +46,19 @@ impl From<HealthHelper> for Health {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Attack {
     pub damage: i32,
+    /// energy a hit takes, heavy hitters are slow
+    #[serde(default = "_default_action_cost")]
+    pub cost: i32,
+}
+
+const fn _default_action_cost() -> i32 {
+    ACTION_COST
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// energy gained per round, actors without it have `scheduler::NORMAL_SPEED`
+pub struct Speed(pub i32);
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Position(pub Point);
 
@@ -116,11 +132,9 @@ const fn _default_true() -> bool {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Player {
     pub current_blueprint: Option<Blueprint>,
-    #[serde(skip)] // TODO
     /// This contains optional because when effects are executing, we need to `Option::take` the ingredients that we
     /// are baking with, so that the effect can't go over and stir them up
     pub passive_equipment: Vec<Option<Equipment>>,
-    #[serde(skip)] // TODO
     /// This contains optional because when effects are executing, we need to `Option::take` the ingredients that we
     /// are baking with, so that the effect can't go over and stir them up
     pub active_equipment: Vec<Option<Equipment>>,
@@ -130,12 +144,65 @@ pub struct Player {
 pub struct Monster {
     #[serde(default)]
     pub tracking: Option<Point>,
+    #[serde(default)]
+    pub awareness: Awareness,
+    /// turns left before it gives up on `tracking`, see `perception`
+    #[serde(default)]
+    pub memory: i32,
+}
+
+impl Monster {
+    pub fn new(awareness: Awareness) -> Monster {
+        Monster {
+            tracking: None,
+            awareness,
+            memory: 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Awareness {
+    /// does nothing, and only notices noise close by
+    Asleep,
+    /// wanders around
+    #[default]
+    Idle,
+    /// heading for something it heard, or where it last saw the player
+    Searching,
+    /// can see the player
+    Hunting,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+/// how far away noises can be heard, see `perception`
+pub struct Hearing {
+    pub range: i32,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Skilled {
     /// (probability_weight, skill) -- default action (e.g. move/hit) has weight of 1.0
     pub skills: Vec<(f32, Skill)>,
+    /// rounds until each of `skills` can be used again, by index
+    #[serde(default)]
+    pub cooldowns: Vec<u32>,
+}
+
+/// One of the final bosses, see `boss`
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Boss {
+    pub attack: BossAttack,
+    /// from the most health to the least, the first one is where it starts
+    pub phases: Vec<BossPhase>,
+    #[serde(default)]
+    pub phase: usize,
+    /// rounds until it can wind up its attack again
+    #[serde(default)]
+    pub recharge: u32,
+    /// where its attack lands on its next turn
+    #[serde(default)]
+    pub telegraph: Vec<Point>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -171,7 +238,7 @@ pub struct Blueprint {
 /// gets removed when we go into a new level
 pub struct Ephermal;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Elemental {
     Fire,
     Water,
@@ -181,11 +248,28 @@ pub enum Elemental {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Rank(pub i32);
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct Slowed {
-    pub duration: u32,
+/// percentage of each element's damage shrugged off, negative for weaknesses, see `damage`
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Resistances(pub HashMap<Elemental, i32>);
+
+/// what stands between an entity and a melee hit, on top of any worn armor, see `damage`
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Defence {
+    /// taken off every hit, which never goes below nothing
+    #[serde(default)]
+    pub armor: i32,
+    /// percent chance to stop a hit outright
+    #[serde(default)]
+    pub block: i32,
+    /// percent chance a hit misses
+    #[serde(default)]
+    pub evasion: i32,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+/// see `status` for what each effect does and how they stack
+pub struct StatusEffects(pub Vec<StatusEffect>);
+
 impl std::fmt::Display for Elemental {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match self {
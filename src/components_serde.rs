@@ -14,16 +14,22 @@ pub enum Component {
     Player(Player),
     Monster(Monster),
     Skilled(Skilled),
+    Boss(Boss),
     Item(Item),
+    Inventory(Inventory),
     Name(Name),
     Grower(Grower),
     Blocker(Blocker),
     Blueprint(Blueprint),
     Ephermal(Ephermal),
     Elemental(Elemental),
+    Resistances(Resistances),
+    Defence(Defence),
     Rank(Rank),
-    Slowed(Slowed),
+    StatusEffects(StatusEffects),
     TempWall(TempWall),
+    Speed(Speed),
+    Hearing(Hearing),
 }
 
 pub trait IsComponent: Debug {}
@@ -37,19 +43,25 @@ impl Component {
             Component::Position(c) => ecs.insert_one(entity, c),
             Component::Renderable(c) => ecs.insert_one(entity, c),
             Component::Viewer(c) => ecs.insert_one(entity, c),
+            Component::Hearing(c) => ecs.insert_one(entity, c),
             Component::Player(c) => ecs.insert_one(entity, c),
             Component::Monster(c) => ecs.insert_one(entity, c),
             Component::Skilled(c) => ecs.insert_one(entity, c),
+            Component::Boss(c) => ecs.insert_one(entity, c),
             Component::Item(c) => ecs.insert_one(entity, c),
+            Component::Inventory(c) => ecs.insert_one(entity, c),
             Component::Name(c) => ecs.insert_one(entity, c),
             Component::Grower(c) => ecs.insert_one(entity, c),
             Component::Blocker(c) => ecs.insert_one(entity, c),
             Component::Blueprint(c) => ecs.insert_one(entity, c),
             Component::Ephermal(c) => ecs.insert_one(entity, c),
             Component::Elemental(c) => ecs.insert_one(entity, c),
+            Component::Resistances(c) => ecs.insert_one(entity, c),
+            Component::Defence(c) => ecs.insert_one(entity, c),
             Component::Rank(c) => ecs.insert_one(entity, c),
-            Component::Slowed(c) => ecs.insert_one(entity, c),
+            Component::StatusEffects(c) => ecs.insert_one(entity, c),
             Component::TempWall(c) => ecs.insert_one(entity, c),
+            Component::Speed(c) => ecs.insert_one(entity, c),
         }
     }
     pub fn apply(&self, mut f: impl FnMut(&dyn IsComponent)) {
@@ -59,19 +71,25 @@ impl Component {
             Component::Position(c) => f(c),
             Component::Renderable(c) => f(c),
             Component::Viewer(c) => f(c),
+            Component::Hearing(c) => f(c),
             Component::Player(c) => f(c),
             Component::Monster(c) => f(c),
             Component::Skilled(c) => f(c),
+            Component::Boss(c) => f(c),
             Component::Item(c) => f(c),
+            Component::Inventory(c) => f(c),
             Component::Name(c) => f(c),
             Component::Grower(c) => f(c),
             Component::Blocker(c) => f(c),
             Component::Blueprint(c) => f(c),
             Component::Ephermal(c) => f(c),
             Component::Elemental(c) => f(c),
+            Component::Resistances(c) => f(c),
+            Component::Defence(c) => f(c),
             Component::Rank(c) => f(c),
-            Component::Slowed(c) => f(c),
+            Component::StatusEffects(c) => f(c),
             Component::TempWall(c) => f(c),
+            Component::Speed(c) => f(c),
         }
     }
 }
@@ -90,7 +108,10 @@ mod tests {
         let and_again: Vec<Component> = serde_json::from_str(&and_back_again).unwrap();
         let data = vec![
             Component::Health(Health { max_hp: 10, hp: 10 }),
-            Component::Attack(Attack { damage: 10 }),
+            Component::Attack(Attack {
+                damage: 10,
+                cost: 100,
+            }),
             Component::Name(Name("Orc".to_string())),
             Component::Renderable(Renderable {
                 glyph: to_cp437('o'),
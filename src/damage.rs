@@ -0,0 +1,164 @@
+//! Every hit goes through here, so resistances apply to all of it. A `Resistances` entry is the percentage of that
+//! element's damage an entity shrugs off: 50 takes half, -50 takes half again as much. Damage with no element is
+//! never resisted.
+//!
+//! Melee attacks have to get past the target's `Defence` first: it might dodge them or block them outright, and
+//! its armor comes off whatever's left, though never past nothing. The player's worn armor adds to theirs.
+
+use hecs::{Entity, World};
+
+use crate::{components::*, equipment::worn_defence};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Damage {
+    pub amount: i32,
+    pub element: Option<Elemental>,
+}
+
+impl Damage {
+    pub fn physical(amount: i32) -> Damage {
+        Damage {
+            amount,
+            element: None,
+        }
+    }
+    pub fn of(amount: i32, element: Elemental) -> Damage {
+        Damage {
+            amount,
+            element: Some(element),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Effectiveness {
+    Normal,
+    Resisted,
+    Weak,
+}
+
+impl Effectiveness {
+    /// Said after the hit, if there's anything to say
+    pub fn message(self, is_player: bool) -> Option<&'static str> {
+        match (self, is_player) {
+            (Effectiveness::Normal, _) => None,
+            (Effectiveness::Resisted, false) => Some("It resists!"),
+            (Effectiveness::Resisted, true) => Some("You resist!"),
+            (Effectiveness::Weak, false) => Some("It's weak to that!"),
+            (Effectiveness::Weak, true) => Some("That hurts!"),
+        }
+    }
+}
+
+impl Resistances {
+    /// How much of `damage` gets through, rounded to the nearest point
+    pub fn scale(&self, damage: &Damage) -> (i32, Effectiveness) {
+        let percent = damage
+            .element
+            .as_ref()
+            .and_then(|e| self.0.get(e))
+            .copied()
+            .unwrap_or(0)
+            .clamp(-100, 100);
+        let amount = (damage.amount * (100 - percent) + 50).div_euclid(100);
+        let effectiveness = match percent {
+            0 => Effectiveness::Normal,
+            p if p > 0 => Effectiveness::Resisted,
+            _ => Effectiveness::Weak,
+        };
+        (amount, effectiveness)
+    }
+}
+
+impl Defence {
+    /// What's left of a hit of `amount` after armor
+    pub fn reduce(&self, amount: i32) -> i32 {
+        (amount - self.armor).max(0)
+    }
+}
+
+impl std::ops::Add for Defence {
+    type Output = Defence;
+    fn add(self, other: Defence) -> Defence {
+        Defence {
+            armor: self.armor + other.armor,
+            block: self.block + other.block,
+            evasion: self.evasion + other.evasion,
+        }
+    }
+}
+
+/// All of an entity's defence, its own and whatever it's wearing
+pub fn defence_of(ecs: &World, entity: Entity) -> Defence {
+    let own = ecs.get::<&Defence>(entity).map_or(Defence::default(), |d| *d);
+    match ecs.get::<&Player>(entity) {
+        Ok(player) => own + worn_defence(&player),
+        Err(_) => own,
+    }
+}
+
+/// Damage after `resistances`, for when the target is already borrowed
+pub fn resisted(resistances: Option<&Resistances>, damage: &Damage) -> (i32, Effectiveness) {
+    match resistances {
+        Some(r) => r.scale(damage),
+        None => (damage.amount, Effectiveness::Normal),
+    }
+}
+
+/// Takes `damage` off the target's health, returning how much it took and how well it resisted, or `None` if it
+/// has no health to take it from
+pub fn deal_damage(
+    ecs: &mut World,
+    target: Entity,
+    damage: &Damage,
+) -> Option<(i32, Effectiveness)> {
+    let (health, resistances) = ecs
+        .query_one_mut::<(&mut Health, Option<&Resistances>)>(target)
+        .ok()?;
+    let (amount, effectiveness) = resisted(resistances, damage);
+    health.hp -= amount;
+    Some((amount, effectiveness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resistances_scale_their_element() {
+        let r = Resistances(
+            [(Elemental::Fire, 50), (Elemental::Air, -50)]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            r.scale(&Damage::of(5, Elemental::Fire)),
+            (3, Effectiveness::Resisted)
+        );
+        assert_eq!(
+            r.scale(&Damage::of(4, Elemental::Air)),
+            (6, Effectiveness::Weak)
+        );
+        assert_eq!(
+            r.scale(&Damage::of(4, Elemental::Water)),
+            (4, Effectiveness::Normal)
+        );
+        assert_eq!(r.scale(&Damage::physical(4)), (4, Effectiveness::Normal));
+        // a burn of 1 still stings
+        assert_eq!(
+            r.scale(&Damage::of(1, Elemental::Fire)),
+            (1, Effectiveness::Resisted)
+        );
+    }
+
+    #[test]
+    fn armor_never_heals() {
+        let defence = Defence {
+            armor: 3,
+            ..Defence::default()
+        };
+        assert_eq!(defence.reduce(5), 2);
+        assert_eq!(defence.reduce(3), 0);
+        assert_eq!(defence.reduce(1), 0);
+    }
+}
@@ -28,6 +28,9 @@ pub fn get_entity_components(entity: hecs::EntityRef<'_>) -> Vec<components_serd
     if let Some(x) = entity.get::<&Viewer>() {
         c.push(Component::Viewer((*x).clone()));
     }
+    if let Some(x) = entity.get::<&Hearing>() {
+        c.push(Component::Hearing((*x).clone()));
+    }
     if let Some(x) = entity.get::<&Player>() {
         c.push(Component::Player((*x).clone()));
     }
@@ -37,9 +40,15 @@ pub fn get_entity_components(entity: hecs::EntityRef<'_>) -> Vec<components_serd
     if let Some(x) = entity.get::<&Skilled>() {
         c.push(Component::Skilled((*x).clone()));
     }
+    if let Some(x) = entity.get::<&Boss>() {
+        c.push(Component::Boss((*x).clone()));
+    }
     if let Some(x) = entity.get::<&Item>() {
         c.push(Component::Item((*x).clone()));
     }
+    if let Some(x) = entity.get::<&Inventory>() {
+        c.push(Component::Inventory((*x).clone()));
+    }
     if let Some(x) = entity.get::<&Name>() {
         c.push(Component::Name((*x).clone()));
     }
@@ -58,11 +67,23 @@ pub fn get_entity_components(entity: hecs::EntityRef<'_>) -> Vec<components_serd
     if let Some(x) = entity.get::<&Elemental>() {
         c.push(Component::Elemental((*x).clone()));
     }
+    if let Some(x) = entity.get::<&Resistances>() {
+        c.push(Component::Resistances((*x).clone()));
+    }
+    if let Some(x) = entity.get::<&Defence>() {
+        c.push(Component::Defence(*x));
+    }
     if let Some(x) = entity.get::<&Rank>() {
         c.push(Component::Rank((*x).clone()));
     }
-    if let Some(x) = entity.get::<&Slowed>() {
-        c.push(Component::Slowed((*x).clone()));
+    if let Some(x) = entity.get::<&StatusEffects>() {
+        c.push(Component::StatusEffects((*x).clone()));
+    }
+    if let Some(x) = entity.get::<&TempWall>() {
+        c.push(Component::TempWall((*x).clone()));
+    }
+    if let Some(x) = entity.get::<&Speed>() {
+        c.push(Component::Speed((*x).clone()));
     }
     c
 }
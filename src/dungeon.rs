@@ -0,0 +1,140 @@
+//! Every floor the player has been on is kept, so they can go back up for whatever they left behind. Floors nobody
+//! is on are parked here, their monsters and items stored as lists of components the same way saves store them.
+
+use std::collections::HashMap;
+
+use bracket_lib::prelude::*;
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::*,
+    components_serde::Component,
+    debug::get_entity_components,
+    map::{floor_rng, item_fill_map, populate_map, Map, Tile},
+    pathing::FlowField,
+    OperatingMode, State,
+};
+
+/// A floor the player isn't on, with everything that was left on it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Level {
+    pub map: Map,
+    pub entities: Vec<Vec<Component>>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Dungeon {
+    pub levels: HashMap<i32, Level>,
+}
+
+impl Dungeon {
+    /// Takes everything that belongs to the current floor out of the world and stores it along with `map`
+    pub fn park(&mut self, ecs: &mut World, map: Map) {
+        let mut gone: Vec<Entity> = ecs
+            .query_mut::<&Ephermal>()
+            .into_iter()
+            .map(|(e, _)| e)
+            .collect();
+        // archetype iteration order isn't stable, entity ids are
+        gone.sort_by_key(|e| e.to_bits());
+        let mut entities = vec![];
+        for entity in gone {
+            let components = get_entity_components(ecs.entity(entity).unwrap());
+            // they come back under new ids, which anything holding on to another entity wouldn't follow
+            assert!(!components
+                .iter()
+                .any(|c| matches!(c, Component::Inventory(_) | Component::Grower(_))));
+            entities.push(components);
+            ecs.despawn(entity).unwrap();
+        }
+        self.levels.insert(map.depth, Level { map, entities });
+    }
+
+    /// Puts a parked floor's things back in the world, returning its map and what was spawned. They get new entity
+    /// ids, so `park` won't take anything with a component that points at another entity
+    pub fn restore(&mut self, ecs: &mut World, depth: i32) -> Option<(Map, Vec<Entity>)> {
+        let level = self.levels.remove(&depth)?;
+        let mut spawned = vec![];
+        for components in level.entities {
+            let entity = ecs.spawn(());
+            for component in components {
+                component.insert(ecs, entity).unwrap();
+            }
+            spawned.push(entity);
+        }
+        Some((level.map, spawned))
+    }
+}
+
+/// Takes the player down to the next floor, or out of the dungeon from the last one
+pub fn descend(state: &mut State) {
+    if state.map.depth == 5 {
+        // we beat the game!!
+        state.operating_mode = OperatingMode::GameWon;
+        return;
+    }
+    travel(state, state.map.depth + 1);
+}
+
+pub fn ascend(state: &mut State) {
+    if state.map.depth > 0 {
+        travel(state, state.map.depth - 1);
+    }
+}
+
+/// Leaves the current floor parked and moves the player onto the stairs they'd have come by on `depth`, making the
+/// floor first if nobody has been there
+fn travel(state: &mut State, depth: i32) {
+    let going_down = depth > state.map.depth;
+    let old = std::mem::replace(&mut state.map, Map::blank(depth, 0, 0));
+    state.dungeon.park(&mut state.ecs, old);
+    state.turn_order.clear();
+    state.turn_order.add(state.player_entity);
+    // the field was worked out on the floor being left
+    state.flow = FlowField::default();
+    // whatever was hit there comes back with a new id, if at all
+    state.events.forget_hits();
+
+    let restored = state.dungeon.restore(&mut state.ecs, depth);
+    let fresh = restored.is_none();
+    match restored {
+        Some((map, entities)) => {
+            state.map = map;
+            for entity in entities {
+                if state.ecs.satisfies::<&Monster>(entity).unwrap() {
+                    state.turn_order.add(entity);
+                }
+            }
+        }
+        None => {
+            state.rng = floor_rng(state.seed, depth);
+            state.map = Map::new(depth, &mut state.rng);
+        }
+    }
+
+    let arrival = if going_down {
+        state.map.start
+    } else {
+        find_tile(&state.map, Tile::Stairs).unwrap_or(state.map.start)
+    };
+    let (position, viewer, health) = state
+        .ecs
+        .query_one_mut::<(&mut Position, &mut Viewer, &mut Health)>(state.player_entity)
+        .unwrap();
+    position.0 = arrival;
+    viewer.dirty = true;
+    if fresh {
+        // only somewhere new is a rest, or the stairs could be paced for health
+        health.hp = health.max_hp;
+        populate_map(state);
+        item_fill_map(state);
+    }
+}
+
+fn find_tile(map: &Map, tile: Tile) -> Option<Point> {
+    map.tiles
+        .iter()
+        .position(|t| *t == tile)
+        .map(|idx| map.index_to_point2d(idx))
+}
@@ -1,4 +1,7 @@
-use std::fmt;
+//! Forged equipment. What each kind of equipment does with each element lives in `raws/equipment.json`: when
+//! it goes off, a list of effect primitives, and the text describing it. This file only interprets that data.
+
+use std::collections::HashMap;
 
 use bracket_lib::prelude::*;
 use hecs::Entity;
@@ -6,141 +9,412 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     blueprint::BPImage,
+    combat::{post, Event, HitKind},
     components::*,
+    damage::Damage,
     essence::Essence,
     math::normalize_pt,
-    util::{get_thing_with_thing_at_pos, push_entity_in_line_to},
+    raws::RAWS,
+    status::{apply_status, StatusEffect, StatusKind},
+    util::get_thing_with_thing_at_pos,
     State,
 };
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum EquipmentType {
-    Sword,   // 1 arg - effect
-    Armor,   // 1 arg - effect
-    Gun,     // 1 arg - effect
-    Grapple, // 1 arg - targetting
+    Sword,   // 1-2 gems - effect
+    Armor,   // 1-3 gems - effect
+    Gun,     // 1-2 gems - effect
+    Grapple, // 1 gem - targetting
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub enum Trigger {
+    /// on whatever the player hits in melee
+    OnAttack,
+    /// on whatever hits the player, even when the hit is blocked
+    OnHit,
+    /// used from the action bar on a chosen tile
+    Targeted,
+}
+
+/// `base + per_power * power`, with power 0 being the weakest essence
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Scaling {
+    #[serde(default)]
+    pub base: i32,
+    #[serde(default)]
+    pub per_power: i32,
+}
+
+impl Scaling {
+    pub fn at(self, power: i32) -> i32 {
+        self.base + self.per_power * power
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub enum EffectPrim {
+    /// hurt the target with the gem's element, see `damage`
+    Damage(Scaling),
+    /// slow the target for this many rounds
+    Slow(Scaling),
+    /// put any other status on the target
+    Status {
+        kind: StatusKind,
+        duration: Scaling,
+        #[serde(default)]
+        power: Scaling,
+    },
+    /// knock the target this many tiles away from the player
+    Push(Scaling),
+    /// drag the target this many tiles toward the player
+    Pull(Scaling),
+    /// drag the target right up to the player
+    PullAll,
+    /// raise a wall lasting this many rounds on the targeted tile, if nothing is there
+    SpawnWall(Scaling),
+    /// add this much to the player's armor while it's worn, see `worn_defence`
+    Armor(Scaling),
+    /// add this percent chance to block a hit outright while it's worn
+    Block(Scaling),
+    /// add this percent chance to be missed while it's worn
+    Evasion(Scaling),
+    /// hop to every monster within `radius` of the last one, doing `effects` to each
+    Chain {
+        radius: i32,
+        message: Option<String>,
+        effects: Vec<EffectPrim>,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ElementEffect {
+    /// slotted into the equipment's `desc`
+    pub text: String,
+    /// said once the effect goes off on a target
+    #[serde(default)]
+    pub message: Option<String>,
+    pub effects: Vec<EffectPrim>,
+}
+
+/// Replaces the single element effects when the slotted gems are exactly `elements`, in any order. Its power
+/// is the gems' powers added up.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Combo {
+    pub elements: Vec<Elemental>,
+    #[serde(flatten)]
+    pub effect: ElementEffect,
+}
+
+/// `text` and `message` can use `{damage}`, `{slow}`, `{status}`, `{push}`, `{pull}`, `{wall}`, `{armor}`,
+/// `{block}` and `{evasion}`, which become the amount of the first such effect at the essence's power. Messages
+/// also get `{name}`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EquipmentDef {
+    pub trigger: Trigger,
+    /// `{element}` is where the slotted element's `text` goes
+    pub desc: String,
+    pub elements: HashMap<Elemental, ElementEffect>,
+    #[serde(default)]
+    pub combos: Vec<Combo>,
 }
 
-#[derive(Debug, Clone)]
+fn same_elements(a: &[Elemental], b: &[Elemental]) -> bool {
+    let count = |v: &[Elemental], e: &Elemental| v.iter().filter(|x| *x == e).count();
+    a.len() == b.len() && a.iter().all(|e| count(a, e) == count(b, e))
+}
+
+impl EquipmentDef {
+    /// What a set of gems does, with the power each part goes off at. A matching combo wins, otherwise every gem
+    /// does its own thing in slot order.
+    pub fn resolve(&self, gems: &[Essence]) -> Vec<(Vec<Elemental>, &ElementEffect, i32)> {
+        let elements: Vec<Elemental> = gems.iter().map(|g| g.element.clone()).collect();
+        if gems.len() > 1 {
+            if let Some(combo) = self
+                .combos
+                .iter()
+                .find(|c| same_elements(&c.elements, &elements))
+            {
+                let power = gems.iter().map(|g| g.power).sum();
+                return vec![(elements, &combo.effect, power)];
+            }
+        }
+        gems.iter()
+            .filter_map(|g| {
+                let eff = self.elements.get(&g.element)?;
+                Some((vec![g.element.clone()], eff, g.power))
+            })
+            .collect()
+    }
+}
+
+pub fn equipment_def(typ: EquipmentType) -> EquipmentDef {
+    RAWS.lock().unwrap().equipment[&typ].clone()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "Blueprint", from = "Blueprint")]
+/// Saved as the blueprint it was forged from, and re-forged on load
 pub struct Equipment {
     pub ingredients: (EquipmentType, Vec<Essence>),
-    pub effect: EquipmentEffect,
     pub img: BPImage,
 }
 
-pub fn print_desc(typ: EquipmentType, ess: &Vec<Option<Essence>>, builder: &mut TextBuilder) {
-    match typ {
-        EquipmentType::Sword => sword_desc(ess, builder),
-        EquipmentType::Armor => armor_desc(ess, builder),
-        EquipmentType::Gun => gun_desc(ess, builder),
-        EquipmentType::Grapple => grapple_desc(ess, builder),
-    };
-}
-
-fn colorize_print_element(
-    fire: &str,
-    water: &str,
-    air: &str,
-    e: Option<Essence>,
-    builder: &mut TextBuilder,
-) {
-    if let Some(e) = e {
-        match e.element {
-            Elemental::Fire => builder.fg(RGB::named(RED)).line_wrap(fire),
-            Elemental::Water => builder.fg(RGB::named(BLUE3)).line_wrap(water),
-            Elemental::Air => builder.fg(RGB::named(SKYBLUE)).line_wrap(air),
-        };
-        match e.power {
-            0 => builder.line_wrap("(v1)"),
-            1 => builder.line_wrap("(v2)"),
-            2 => builder.line_wrap("(v3)"),
-            _ => panic!("invalid power"),
-        };
-    } else {
-        builder.fg(RGB::named(GRAY)).line_wrap("___");
+impl Equipment {
+    pub fn trigger(&self) -> Trigger {
+        equipment_def(self.ingredients.0).trigger
     }
 }
 
-fn sword_desc(ess: &Vec<Option<Essence>>, builder: &mut TextBuilder) {
-    colorize_print_element(
-        "Damage",
-        "Freeze and slow",
-        "Blast back",
-        ess[0].clone(),
-        builder,
-    );
-    builder
-        .fg(RGB::named(WHITE))
-        .line_wrap("your target on hit.");
+impl From<Equipment> for Blueprint {
+    fn from(value: Equipment) -> Self {
+        Blueprint {
+            img: value.img,
+            equipment: value.ingredients.0,
+            filled: value.ingredients.1.into_iter().enumerate().collect(),
+        }
+    }
 }
 
-fn armor_desc(ess: &Vec<Option<Essence>>, builder: &mut TextBuilder) {
-    colorize_print_element(
-        "Damage",
-        "Freeze and slow",
-        "Blast back",
-        ess[0].clone(),
-        builder,
-    );
-    builder
-        .fg(RGB::named(WHITE))
-        .line_wrap("your attacker when hit, and also block damage.");
+impl From<Blueprint> for Equipment {
+    fn from(value: Blueprint) -> Self {
+        build_blueprint(&value)
+    }
 }
 
-fn grapple_desc(ess: &Vec<Option<Essence>>, builder: &mut TextBuilder) {
-    builder.fg(RGB::named(WHITE)).line_wrap("Yank");
-    colorize_print_element(
-        "and damage an enemy, bringing it",
-        "one enemy all the way",
-        "enemies chain-lightning style",
-        ess[0].clone(),
-        builder,
-    );
-    builder.fg(RGB::named(WHITE)).line_wrap("to you.");
+fn fill_in(template: &str, effects: &[EffectPrim], power: i32) -> String {
+    let mut out = template.to_string();
+    for eff in effects {
+        let (key, amount) = match eff {
+            EffectPrim::Damage(s) => ("{damage}", s.at(power)),
+            EffectPrim::Slow(s) => ("{slow}", s.at(power)),
+            EffectPrim::Status { duration, .. } => ("{status}", duration.at(power)),
+            EffectPrim::Push(s) => ("{push}", s.at(power)),
+            EffectPrim::Pull(s) => ("{pull}", s.at(power)),
+            EffectPrim::SpawnWall(s) => ("{wall}", s.at(power)),
+            EffectPrim::Armor(s) => ("{armor}", s.at(power)),
+            EffectPrim::Block(s) => ("{block}", s.at(power)),
+            EffectPrim::Evasion(s) => ("{evasion}", s.at(power)),
+            EffectPrim::PullAll | EffectPrim::Chain { .. } => continue,
+        };
+        out = out.replace(key, &amount.to_string());
+    }
+    out
 }
 
-fn gun_desc(ess: &Vec<Option<Essence>>, builder: &mut TextBuilder) {
-    builder.fg(RGB::named(WHITE)).line_wrap("Shoot a");
-    colorize_print_element(
-        "damaging bullet at an enemy.",
-        "bullet, creating a temporary wall.",
-        "pushing bullet at an enemy.",
-        ess[0].clone(),
-        builder,
-    );
+pub fn element_color(element: &Elemental) -> RGB {
+    match element {
+        Elemental::Fire => RGB::named(RED),
+        Elemental::Water => RGB::named(BLUE3),
+        Elemental::Air => RGB::named(SKYBLUE),
+    }
 }
 
-#[derive(Debug, Clone)]
-pub enum EquipmentEffect {
-    Active(ActiveEquipment),
-    Passive(PassiveEquipment),
+/// Every slot has to be filled before we know which effect it'll be, until then only the gems are shown
+pub fn print_desc(typ: EquipmentType, ess: &[Option<Essence>], builder: &mut TextBuilder) {
+    let def = equipment_def(typ);
+    let (before, after) = def.desc.split_once("{element}").unwrap_or((def.desc.as_str(), ""));
+    if !before.trim().is_empty() {
+        builder.fg(RGB::named(WHITE)).line_wrap(before.trim());
+    }
+    let gems: Vec<Essence> = ess.iter().flatten().cloned().collect();
+    if gems.len() < ess.len() {
+        for e in ess.iter() {
+            match e {
+                Some(e) if ess.len() > 1 => {
+                    builder
+                        .fg(element_color(&e.element))
+                        .line_wrap(&format!("{}(v{})", e.element, e.power + 1));
+                }
+                _ => {
+                    builder.fg(RGB::named(GRAY)).line_wrap("___");
+                }
+            }
+        }
+    } else {
+        for (i, (elements, eff, power)) in def.resolve(&gems).into_iter().enumerate() {
+            if i > 0 {
+                builder.fg(RGB::named(WHITE)).line_wrap("and");
+            }
+            builder
+                .fg(element_color(&elements[0]))
+                .line_wrap(&fill_in(&eff.text, &eff.effects, power));
+            let version = if elements.len() > 1 {
+                // a combo's power is its gems' added up, which is no version any one gem could be
+                let versions: Vec<String> =
+                    gems.iter().map(|g| format!("v{}", g.power + 1)).collect();
+                versions.join("+")
+            } else {
+                format!("v{}", power + 1)
+            };
+            builder.line_wrap(&format!("({})", version));
+        }
+    }
+    if !after.trim().is_empty() {
+        builder.fg(RGB::named(WHITE)).line_wrap(after.trim());
+    }
 }
 
-#[derive(Clone)]
-pub enum ActiveEquipment {
-    TargetEffect(fn(&mut State, Point, &Vec<Essence>)),
+fn announce(s: &mut State, message: &str, effects: &[EffectPrim], power: i32, target: Entity) {
+    let Ok(name) = s.ecs.query_one_mut::<&Name>(target) else { return };
+    let message = fill_in(message, effects, power).replace("{name}", &name.0);
+    s.messages.enqueue_message(&message);
 }
 
-impl fmt::Debug for ActiveEquipment {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::TargetEffect(_) => write!(f, "TargetEffect"),
+/// Monsters hopping out from `first` at `pt`, each one within `radius` of the one before
+fn chain_targets(s: &mut State, first: Entity, mut pt: Point, radius: i32) -> Vec<Entity> {
+    let mut hit = vec![first];
+    'chain: loop {
+        for x in -radius..=radius {
+            for y in -radius..=radius {
+                let at = pt + Point::new(x, y);
+                if !s.map.in_bounds(at) {
+                    continue;
+                }
+                if let Some(potential) = get_thing_with_thing_at_pos::<&Monster>(s, at) {
+                    if !hit.contains(&potential) {
+                        pt = at;
+                        hit.push(potential);
+                        continue 'chain;
+                    }
+                }
+            }
         }
+        break;
     }
+    hit.split_off(1)
 }
 
-#[derive(Clone)]
-pub enum PassiveEquipment {
-    AttackEffect(fn(&mut State, Entity, &Vec<Essence>)),
-    GotHitEffect(fn(&mut State, Entity, &Vec<Essence>) -> i32),
+/// Does `effects` to `target`, or to the tile `pt` when there's nothing there. Any damage is of `element`.
+fn apply_effects(
+    s: &mut State,
+    effects: &[EffectPrim],
+    power: i32,
+    element: &Option<Elemental>,
+    target: Option<Entity>,
+    pt: Point,
+) {
+    let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
+    for eff in effects {
+        // worn, not set off
+        if matches!(
+            eff,
+            EffectPrim::Armor(_) | EffectPrim::Block(_) | EffectPrim::Evasion(_)
+        ) {
+            continue;
+        }
+        if let EffectPrim::SpawnWall(duration) = eff {
+            let idx = s.map.point2d_to_index(pt);
+            if s.map.tile_contents[idx].is_empty() {
+                s.ecs.spawn((
+                    Name("Water wall".to_string()),
+                    Position(pt),
+                    Blocker {},
+                    TempWall {
+                        duration: duration.at(power),
+                    },
+                    Renderable {
+                        glyph: to_cp437('#'),
+                        fg: RGB::from_hex("#7b68ee").unwrap(),
+                        bg: RGB::from_hex("#000000").unwrap(),
+                        layer: 1,
+                    },
+                ));
+                s.map.changes += 1;
+            }
+            continue;
+        }
+        let Some(e) = target else { continue };
+        // earlier effects may have moved it
+        let Ok(target_pos) = s.ecs.query_one_mut::<&Position>(e).map(|p| p.0) else { continue };
+        match eff {
+            EffectPrim::Damage(amount) => {
+                let damage = Damage {
+                    amount: amount.at(power),
+                    element: element.clone(),
+                };
+                post(
+                    s,
+                    Event::Hit {
+                        source: Some(s.player_entity),
+                        target: e,
+                        damage,
+                        kind: HitKind::Effect,
+                    },
+                );
+            }
+            EffectPrim::Slow(duration) => apply_status(
+                &mut s.ecs,
+                e,
+                StatusEffect {
+                    kind: StatusKind::Slow,
+                    duration: duration.at(power).max(0) as u32,
+                    power: 0,
+                },
+            ),
+            EffectPrim::Status {
+                kind,
+                duration,
+                power: status_power,
+            } => apply_status(
+                &mut s.ecs,
+                e,
+                StatusEffect {
+                    kind: *kind,
+                    duration: duration.at(power).max(0) as u32,
+                    power: status_power.at(power),
+                },
+            ),
+            EffectPrim::Push(distance) => {
+                let dest = target_pos + normalize_pt(target_pos - player_pos) * distance.at(power);
+                post(s, Event::Push { entity: e, dest });
+            }
+            EffectPrim::Pull(distance) => {
+                let dest = target_pos + normalize_pt(player_pos - target_pos) * distance.at(power);
+                post(s, Event::Push { entity: e, dest });
+            }
+            EffectPrim::PullAll => post(
+                s,
+                Event::Push {
+                    entity: e,
+                    dest: player_pos,
+                },
+            ),
+            EffectPrim::Chain {
+                radius,
+                message,
+                effects,
+            } => {
+                // hop from where it was hit, not from wherever it got moved to
+                for next in chain_targets(s, e, pt, *radius) {
+                    if let Some(message) = message {
+                        announce(s, message, effects, power, next);
+                    }
+                    let next_pos = s.ecs.query_one_mut::<&Position>(next).unwrap().0;
+                    apply_effects(s, effects, power, element, Some(next), next_pos);
+                }
+            }
+            EffectPrim::Armor(_)
+            | EffectPrim::Block(_)
+            | EffectPrim::Evasion(_)
+            | EffectPrim::SpawnWall(_) => unreachable!(),
+        }
+    }
 }
 
-impl fmt::Debug for PassiveEquipment {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::AttackEffect(_) => write!(f, "AttackEffect"),
-            Self::GotHitEffect(_) => write!(f, "GotHitEffect"),
+fn run_equipment(s: &mut State, equip: &Equipment, target: Option<Entity>, pt: Point) {
+    let def = equipment_def(equip.ingredients.0);
+    for (elements, eff, power) in def.resolve(&equip.ingredients.1) {
+        if let (Some(e), Some(message)) = (target, &eff.message) {
+            announce(s, message, &eff.effects, power, e);
         }
+        // a combo of different elements is none of them, so nothing resists it
+        let element = match elements.as_slice() {
+            [element] => Some(element.clone()),
+            _ => None,
+        };
+        apply_effects(s, &eff.effects, power, &element, target, pt);
     }
 }
 
@@ -150,8 +424,8 @@ pub fn execute_active_target(state: &mut State, ability_idx: usize, target: Poin
         .query_one_mut::<&mut Player>(state.player_entity)
         .unwrap();
     let equip = player.active_equipment[ability_idx].take().unwrap();
-    let EquipmentEffect::Active(ActiveEquipment::TargetEffect(eff)) = equip.effect else {panic!()};
-    eff(state, target, &equip.ingredients.1);
+    let monster = get_thing_with_thing_at_pos::<&Monster>(state, target);
+    run_equipment(state, &equip, monster, target);
     let player = state
         .ecs
         .query_one_mut::<&mut Player>(state.player_entity)
@@ -159,7 +433,8 @@ pub fn execute_active_target(state: &mut State, ability_idx: usize, target: Poin
     player.active_equipment[ability_idx] = Some(equip);
 }
 
-pub fn execute_attack_effects(state: &mut State, target: Entity) {
+/// Sets off every passive piece with `trigger` on `target`
+fn execute_passive(state: &mut State, trigger: Trigger, target: Entity) {
     let player = state
         .ecs
         .query_one_mut::<&mut Player>(state.player_entity)
@@ -167,17 +442,14 @@ pub fn execute_attack_effects(state: &mut State, target: Entity) {
     let mut equip = vec![];
     for (i, eq_maybe) in player.passive_equipment.iter_mut().enumerate() {
         let Some(eq) = eq_maybe else {continue};
-        if matches!(
-            eq.effect,
-            EquipmentEffect::Passive(PassiveEquipment::AttackEffect(_))
-        ) {
+        if eq.trigger() == trigger {
             equip.push((i, eq_maybe.take().unwrap()));
         }
     }
     for (i, eq) in equip {
-        let EquipmentEffect::Passive(PassiveEquipment::AttackEffect(eff)) =
-                                eq.effect else {panic!()};
-        eff(state, target, &eq.ingredients.1);
+        if let Ok(pos) = state.ecs.query_one_mut::<&Position>(target).map(|p| p.0) {
+            run_equipment(state, &eq, Some(target), pos);
+        }
         let player = state
             .ecs
             .query_one_mut::<&mut Player>(state.player_entity)
@@ -187,290 +459,105 @@ pub fn execute_attack_effects(state: &mut State, target: Entity) {
     }
 }
 
-pub fn execute_defence_effects(state: &mut State, target: Entity) -> i32 {
-    let player = state
-        .ecs
-        .query_one_mut::<&mut Player>(state.player_entity)
-        .unwrap();
-    let mut equip = vec![];
-    for (i, eq_maybe) in player.passive_equipment.iter_mut().enumerate() {
-        let Some(eq) = eq_maybe else {continue};
-        if matches!(
-            eq.effect,
-            EquipmentEffect::Passive(PassiveEquipment::GotHitEffect(_))
-        ) {
-            equip.push((i, eq_maybe.take().unwrap()));
+pub fn execute_attack_effects(state: &mut State, target: Entity) {
+    execute_passive(state, Trigger::OnAttack, target);
+}
+
+pub fn execute_defence_effects(state: &mut State, target: Entity) {
+    execute_passive(state, Trigger::OnHit, target);
+}
+
+/// What the passive pieces add to the player's `Defence` just by being worn
+pub fn worn_defence(player: &Player) -> Defence {
+    let mut total = Defence::default();
+    // a piece that's going off is out of its slot for the moment
+    for equip in player.passive_equipment.iter().flatten() {
+        let def = equipment_def(equip.ingredients.0);
+        for (_, eff, power) in def.resolve(&equip.ingredients.1) {
+            for prim in eff.effects.iter() {
+                match prim {
+                    EffectPrim::Armor(s) => total.armor += s.at(power),
+                    EffectPrim::Block(s) => total.block += s.at(power),
+                    EffectPrim::Evasion(s) => total.evasion += s.at(power),
+                    _ => {}
+                }
+            }
         }
     }
-    let mut blocked = 0;
-    for (i, eq) in equip {
-        let EquipmentEffect::Passive(PassiveEquipment::GotHitEffect(eff)) =
-                                eq.effect else {panic!()};
-        blocked += eff(state, target, &eq.ingredients.1);
-        let player = state
-            .ecs
-            .query_one_mut::<&mut Player>(state.player_entity)
-            .unwrap();
-        debug_assert!(player.passive_equipment[i].is_none());
-        player.passive_equipment[i] = Some(eq);
-    }
-    return blocked;
+    total
 }
 
 pub fn build_blueprint(bp: &Blueprint) -> Equipment {
-    let mut gems = vec![];
-    for i in 0..bp.filled.len() {
-        gems.push(bp.filled[i].1.clone());
+    let mut filled = bp.filled.clone();
+    filled.sort_by_key(|(slot, _)| *slot);
+    let gems = filled.into_iter().map(|(_, gem)| gem).collect();
+    Equipment {
+        ingredients: (bp.equipment, gems),
+        img: bp.img,
     }
-    match bp.equipment {
-        EquipmentType::Armor => {
-            debug_assert!(gems.len() == 1);
-            let eff = match gems[0].element {
-                Elemental::Fire => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let (health, name) = s.ecs.query_one_mut::<(&mut Health, &Name)>(e).unwrap();
-                    let dam = (gems[0].power + 1) * 2;
-                    health.hp -= dam;
-                    s.messages.enqueue_message(&format!(
-                        "Your armor hits the attacking {} for {} damage.",
-                        name.0, dam
-                    ));
-                    return gems[0].power + 1;
-                },
-                Elemental::Water => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let slowed = s.ecs.query_one_mut::<Option<&Slowed>>(e).unwrap().cloned();
-                    if let Some(slowed) = slowed {
-                        s.ecs
-                            .insert_one(
-                                e,
-                                Slowed {
-                                    duration: (gems[0].power as u32 + 1) * 1 + slowed.duration,
-                                },
-                            )
-                            .unwrap();
-                    } else {
-                        s.ecs
-                            .insert_one(
-                                e,
-                                Slowed {
-                                    duration: (gems[0].power as u32 + 1) * 1,
-                                },
-                            )
-                            .unwrap();
-                    }
-                    let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                    s.messages
-                        .enqueue_message(&format!("Your armor slows the attacking {}.", name.0,));
-                    return gems[0].power + 1;
-                },
-                Elemental::Air => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                    let target_pos = s.ecs.query_one_mut::<&Position>(e).unwrap().0;
-                    let dest =
-                        target_pos + normalize_pt(target_pos - player_pos) * (gems[0].power + 1);
-                    push_entity_in_line_to(s, e, dest);
-                    let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                    s.messages.enqueue_message(&format!(
-                        "Your armor blasts back the attacking {}.",
-                        name.0,
-                    ));
-                    return gems[0].power + 1;
-                },
-            };
-            let eff = EquipmentEffect::Passive(PassiveEquipment::GotHitEffect(eff));
-            return Equipment {
-                ingredients: (EquipmentType::Armor, gems),
-                effect: eff,
-                img: bp.img,
-            };
-        }
-        EquipmentType::Sword => {
-            debug_assert!(gems.len() == 1);
-            let eff = match gems[0].element {
-                Elemental::Fire => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let (health, name) = s.ecs.query_one_mut::<(&mut Health, &Name)>(e).unwrap();
-                    let dam = (gems[0].power + 1) * 2;
-                    health.hp -= dam;
-                    s.messages.enqueue_message(&format!(
-                        "Your sword flames, dealing {} extra damage to the {}.",
-                        dam, name.0
-                    ));
-                },
-                Elemental::Water => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let slowed = s.ecs.query_one_mut::<Option<&Slowed>>(e).unwrap().cloned();
-                    if let Some(slowed) = slowed {
-                        s.ecs
-                            .insert_one(
-                                e,
-                                Slowed {
-                                    duration: (gems[0].power as u32 + 1) * 1 + slowed.duration,
-                                },
-                            )
-                            .unwrap();
-                    } else {
-                        s.ecs
-                            .insert_one(
-                                e,
-                                Slowed {
-                                    duration: (gems[0].power as u32 + 1) * 1,
-                                },
-                            )
-                            .unwrap();
-                    }
-                    let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                    s.messages.enqueue_message(&format!(
-                        "Your sword glistens with ice, slowing the {}.",
-                        name.0
-                    ));
-                },
-                Elemental::Air => |s: &mut State, e, gems: &Vec<Essence>| {
-                    let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                    let target_pos = s.ecs.query_one_mut::<&Position>(e).unwrap().0;
-                    let dest =
-                        target_pos + normalize_pt(target_pos - player_pos) * (gems[0].power + 1);
-                    push_entity_in_line_to(s, e, dest);
-                    let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                    s.messages
-                        .enqueue_message(&format!("Your sword blows back the {}.", name.0));
-                },
-            };
-            let eff = EquipmentEffect::Passive(PassiveEquipment::AttackEffect(eff));
-            return Equipment {
-                ingredients: (EquipmentType::Sword, gems),
-                effect: eff,
-                img: bp.img,
-            };
-        }
-        EquipmentType::Grapple => {
-            debug_assert!(gems.len() == 1);
-            let eff = match gems[0].element {
-                Elemental::Fire => |s: &mut State, pt, gems: &Vec<Essence>| {
-                    if let Some(e) = get_thing_with_thing_at_pos::<&Monster>(s, pt) {
-                        let player_pos =
-                            s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                        let dest = pt + normalize_pt(player_pos - pt) * (gems[0].power + 2);
-                        push_entity_in_line_to(s, e, dest);
-                        let health = s.ecs.query_one_mut::<&mut Health>(e).unwrap();
-                        let dam = (gems[0].power + 1) * 1;
-                        health.hp -= dam;
-                        let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                        s.messages.enqueue_message(&format!(
-                            "You hook the {} and deal {dam} damage.",
-                            name.0
-                        ));
-                    }
-                },
-                Elemental::Water => |s: &mut State, pt, _gems: &Vec<Essence>| {
-                    if let Some(e) = get_thing_with_thing_at_pos::<&Monster>(s, pt) {
-                        let player_pos =
-                            s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                        push_entity_in_line_to(s, e, player_pos);
-                        let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                        s.messages
-                            .enqueue_message(&format!("You hook the {}.", name.0));
-                    }
-                },
-                Elemental::Air => |s: &mut State, mut pt, gems: &Vec<Essence>| {
-                    let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                    let mut targets = Vec::new();
-                    if let Some(mut target) = get_thing_with_thing_at_pos::<&Monster>(s, pt) {
-                        let dest = pt + normalize_pt(player_pos - pt) * (gems[0].power + 1);
-                        targets.push((target, dest));
-                        'chain: loop {
-                            for x in -2..=2 {
-                                for y in -2..=2 {
-                                    if let Some(potential) = get_thing_with_thing_at_pos::<&Monster>(
-                                        s,
-                                        pt + Point::new(x, y),
-                                    ) {
-                                        if targets.iter().all(|(e, _)| *e != potential) {
-                                            pt += Point::new(x, y);
-                                            target = potential;
-                                            let dest = pt
-                                                + normalize_pt(player_pos - pt)
-                                                    * (gems[0].power + 2);
-                                            targets.push((target, dest));
-                                            continue 'chain;
-                                        }
-                                    }
-                                }
-                            }
-                            break;
-                        }
-                    }
-                    let mut first = true;
-                    for (e, dest) in targets {
-                        let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                        if first {
-                            s.messages
-                                .enqueue_message(&format!("You hook the {}...", name.0));
-                            first = false;
-                        } else {
-                            s.messages
-                                .enqueue_message(&format!("...and the {}...", name.0));
-                        }
-                        push_entity_in_line_to(s, e, dest);
-                    }
-                },
-            };
-            let eff = EquipmentEffect::Active(ActiveEquipment::TargetEffect(eff));
-            return Equipment {
-                ingredients: (EquipmentType::Grapple, gems),
-                effect: eff,
-                img: bp.img,
-            };
-        }
-        EquipmentType::Gun => {
-            debug_assert!(gems.len() == 1);
-            let eff = match gems[0].element {
-                Elemental::Fire => |s: &mut State, pt, gems: &Vec<Essence>| {
-                    if let Some(e) = get_thing_with_thing_at_pos::<&Monster>(s, pt) {
-                        let health = s.ecs.query_one_mut::<&mut Health>(e).unwrap();
-                        let dam = (gems[0].power + 1) * 2;
-                        health.hp -= dam;
-                        let name = s.ecs.query_one_mut::<&Name>(e).unwrap();
-                        s.messages.enqueue_message(&format!(
-                            "You shoot the {} and deal {dam} damage.",
-                            name.0
-                        ));
-                    }
-                },
-                Elemental::Water => |s: &mut State, pt, gems: &Vec<Essence>| {
-                    let idx = s.map.point2d_to_index(pt);
-                    if s.map.tile_contents[idx].is_empty() {
-                        let i = s.ecs.spawn((
-                            Name("Water wall".to_string()),
-                            Position(pt),
-                            Blocker {},
-                            TempWall {
-                                duration: (gems[0].power + 1) * 2 + 1,
-                            },
-                            Renderable {
-                                glyph: to_cp437('#'),
-                                fg: RGB::from_hex("#7b68ee").unwrap(),
-                                bg: RGB::from_hex("#000000").unwrap(),
-                                layer: 1,
-                            },
-                        ));
-                        s.turn_order.push_back(i);
-                    }
-                },
-                Elemental::Air => |s: &mut State, pt, gems: &Vec<Essence>| {
-                    let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
-                    if let Some(target) = get_thing_with_thing_at_pos::<&Monster>(s, pt) {
-                        let dest = pt + normalize_pt(pt - player_pos) * (gems[0].power + 1);
-                        push_entity_in_line_to(s, target, dest);
-                        let name = s.ecs.query_one_mut::<&Name>(target).unwrap();
-                        s.messages
-                            .enqueue_message(&format!("You blast the {} backwards.", name.0));
-                    }
-                },
-            };
-            let eff = EquipmentEffect::Active(ActiveEquipment::TargetEffect(eff));
-            return Equipment {
-                ingredients: (EquipmentType::Gun, gems),
-                effect: eff,
-                img: bp.img,
-            };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_piece_covers_every_element() {
+        let defs: HashMap<EquipmentType, EquipmentDef> =
+            serde_json::from_str(include_str!("../raws/equipment.json")).unwrap();
+        for typ in [
+            EquipmentType::Sword,
+            EquipmentType::Armor,
+            EquipmentType::Gun,
+            EquipmentType::Grapple,
+        ] {
+            let def = &defs[&typ];
+            assert!(def.desc.contains("{element}"), "{typ:?}");
+            for element in [Elemental::Fire, Elemental::Water, Elemental::Air] {
+                assert!(def.elements.contains_key(&element), "{typ:?} {element}");
+            }
         }
     }
+
+    #[test]
+    fn descriptions_match_numbers() {
+        let effects = vec![
+            EffectPrim::Damage(Scaling {
+                base: 2,
+                per_power: 2,
+            }),
+            EffectPrim::Armor(Scaling {
+                base: 1,
+                per_power: 1,
+            }),
+            EffectPrim::Block(Scaling {
+                base: 10,
+                per_power: 5,
+            }),
+        ];
+        assert_eq!(
+            fill_in("Deal {damage}, armor {armor}, block {block}%.", &effects, 2),
+            "Deal 6, armor 3, block 20%."
+        );
+    }
+
+    #[test]
+    fn combos_win_over_single_elements() {
+        let defs: HashMap<EquipmentType, EquipmentDef> =
+            serde_json::from_str(include_str!("../raws/equipment.json")).unwrap();
+        let sword = &defs[&EquipmentType::Sword];
+        let gem = |element, power| Essence { element, power };
+
+        let parts = sword.resolve(&[gem(Elemental::Air, 1), gem(Elemental::Fire, 2)]);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].2, 3);
+        assert!(parts[0].1.effects.iter().any(|e| matches!(e, EffectPrim::Damage(_))));
+        assert!(parts[0].1.effects.iter().any(|e| matches!(e, EffectPrim::Push(_))));
+
+        // no combo for these, so each gem goes off on its own
+        let parts = sword.resolve(&[gem(Elemental::Fire, 0), gem(Elemental::Water, 1)]);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].0, vec![Elemental::Water]);
+        assert_eq!(parts[1].2, 1);
+    }
 }
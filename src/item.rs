@@ -1,21 +1,32 @@
 use bracket_lib::terminal::Point;
 use hecs::Entity;
 
-use crate::{components::*, raws::RAWS, State};
+use crate::{
+    components::*,
+    raws::RAWS,
+    spawn_table::{lookup, pick, SpawnEntry},
+    State,
+};
 
-pub fn spawn_item(state: &mut State, dl: i32, pos: Point) -> Entity {
+/// Picks a kind of item for the current floor from the spawn table
+pub fn pick_item(state: &mut State) -> Option<SpawnEntry> {
+    let raws = RAWS.lock().unwrap();
+    pick(&raws.table.items, state.map.depth, |_| 1, &mut state.rng).cloned()
+}
+
+pub fn spawn_item(state: &mut State, entry: &SpawnEntry, pos: Point) -> Entity {
     let entity = state.ecs.spawn(());
     {
         let raws = RAWS.lock().unwrap();
-        let items = &raws.items[&dl];
-        let item_of_choice = state.rng.range(0, items.len());
-        for component in items[item_of_choice].iter() {
+        let components = lookup(&raws.items, entry.rank, &entry.name)
+            .unwrap_or_else(|| panic!("No item called {} at rank {}", entry.name, entry.rank));
+        for component in components.iter() {
             component.clone().insert(&mut state.ecs, entity).unwrap();
         }
     }
     state
         .ecs
-        .insert(entity, (Item {}, Position(pos), Ephermal, Rank(dl)))
+        .insert(entity, (Item {}, Position(pos), Ephermal, Rank(entry.rank)))
         .unwrap();
     entity
 }
@@ -0,0 +1,505 @@
+use bracket_lib::prelude::*;
+use components::*;
+use equipment::execute_active_target;
+use hecs::{Entity, World};
+use map::{item_fill_map, populate_map};
+use mapping::Command;
+use messages::MessageLog;
+use monster::monster_act;
+use scheduler::{Scheduler, ACTION_COST};
+use systems::*;
+use ui::{draw_main_menu, update_main_menu, ExamineUIRes, MainMenuRes};
+
+pub mod blueprint;
+pub mod boss;
+pub mod combat;
+pub mod components;
+pub mod components_serde;
+pub mod damage;
+pub mod debug;
+pub mod dungeon;
+pub mod equipment;
+pub mod essence;
+pub mod item;
+pub mod map;
+pub mod map_builders;
+pub mod mapping;
+pub mod math;
+pub mod messages;
+pub mod monster;
+pub mod pathing;
+pub mod perception;
+pub mod player;
+pub mod raws;
+pub mod replay;
+pub mod save;
+pub mod scheduler;
+pub mod skill;
+pub mod spawn_table;
+pub mod status;
+pub mod systems;
+pub mod ui;
+pub mod util;
+
+pub const WINDOW_WIDTH: i32 = 100;
+pub const WINDOW_HEIGHT: i32 = 70;
+
+pub struct State {
+    pub ecs: World,
+    pub map: map::Map,
+    /// the floors the player isn't on
+    pub dungeon: dungeon::Dungeon,
+    pub player_entity: Entity,
+    pub rng: RandomNumberGenerator,
+    /// everything random in a run comes from this, see `map::floor_rng`
+    pub seed: u64,
+    pub messages: MessageLog,
+    pub has_moved: bool,
+    pub turn_order: Scheduler,
+    /// what the player's last action sounded like, see `perception`
+    pub noises: Vec<perception::Noise>,
+    /// what monsters have fired since the player's last action, see `skill`
+    pub shots: Vec<skill::Shot>,
+    /// what's happening in the fight right now, see `combat`
+    pub events: combat::Events,
+    pub stats: combat::Stats,
+    /// the way to the player, rebuilt when needed so it never needs saving
+    pub flow: pathing::FlowField,
+    pub operating_mode: OperatingMode,
+    pub debug: bool,
+    pub recorder: Option<replay::Recorder>,
+    /// while this is set, commands come from a replay instead of the keyboard
+    pub playback: Option<replay::Playback>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OperatingMode {
+    WaitingForInput,
+    Ticking,
+    OpenInventory(ui::InvUIState),
+    OpenMessageLog,
+    OpenExamine(ui::ExamineUIState),
+    EquipmentTargetting {
+        state: ui::ExamineUIState,
+        equipment: usize,
+    },
+    MainMenu(ui::MainMenuState),
+    GameOver,
+    GameWon,
+    EquipmentExamining(ui::EquipExamineState),
+    HelpMenu,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GameOptions {
+    /// Start on the main menu rather than straight in the first level
+    pub main_menu: bool,
+    pub debug: bool,
+    /// Write every command to `replay::REPLAY_PATH`
+    pub record: bool,
+}
+
+/// Seeds are kept short so they are easy to read off the sidebar and type back in
+pub const MAX_SEED_DIGITS: usize = 9;
+
+pub fn random_seed() -> u64 {
+    RandomNumberGenerator::new().range(0, 10u64.pow(MAX_SEED_DIGITS as u32))
+}
+
+/// Starts the run a replay was recorded from, or loads the save it was, with the replay queued up to play
+pub fn replay_game(replay: replay::Replay, options: GameOptions) -> State {
+    let mut state = match &replay.save {
+        Some(json) => {
+            let mut state = save::load_game(json).expect("The replay's save is broken");
+            state.debug = options.debug;
+            state
+        }
+        None => State::new_game(replay.seed, options),
+    };
+    state.playback = Some(replay::Playback::new(replay));
+    state
+}
+
+/// Raws, blueprint images and menu images have to be loaded before a game can be made
+pub fn load_resources() {
+    raws::load_raws();
+    blueprint::load_blueprints();
+    ui::load_menus_xp();
+}
+
+impl State {
+    pub fn new_game(seed: u64, options: GameOptions) -> State {
+        let mut rng = map::floor_rng(seed, 0);
+        let mut world = World::new();
+        let map = map::Map::new(0, &mut rng);
+        let player_pos = map.start;
+        let player_entity = world.spawn((
+            Health { max_hp: 30, hp: 30 },
+            Position(player_pos),
+            Player {
+                current_blueprint: None,
+                passive_equipment: vec![],
+                active_equipment: vec![],
+            },
+            Viewer {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            },
+            Renderable {
+                glyph: to_cp437('@'),
+                fg: RGB::named(GREEN),
+                bg: RGB::named(BLACK),
+                layer: 1,
+            },
+            Inventory {
+                contents: Vec::new(),
+            },
+            Name("Bob".to_string()),
+            Grower::Empty,
+            Attack {
+                damage: 3,
+                cost: ACTION_COST,
+            },
+            Defence {
+                armor: 1,
+                block: 0,
+                evasion: 5,
+            },
+        ));
+
+        let operating_mode = if options.main_menu {
+            OperatingMode::MainMenu(ui::MainMenuState {
+                selection: 0,
+                xpfile: XpFile::from_resource("../assets/main-menu.xp").unwrap(),
+                looking_at_help: false,
+                can_continue: save::save_exists(),
+                seed: seed.to_string(),
+            })
+        } else {
+            OperatingMode::Ticking
+        };
+
+        let mut state = State {
+            ecs: world,
+            map,
+            dungeon: dungeon::Dungeon::default(),
+            player_entity,
+            rng,
+            seed,
+            messages: MessageLog {
+                log: Vec::new(),
+                current_messages: Vec::new(),
+            },
+            has_moved: false,
+            turn_order: Scheduler::default(),
+            noises: Vec::new(),
+            shots: Vec::new(),
+            events: combat::Events::default(),
+            stats: combat::Stats::default(),
+            flow: pathing::FlowField::default(),
+            operating_mode,
+            debug: options.debug,
+            recorder: None,
+            playback: None,
+        };
+        if options.record {
+            state.recorder = replay::Recorder::create(replay::REPLAY_PATH, seed, None).ok();
+        }
+
+        state.turn_order.add(player_entity);
+
+        populate_map(&mut state);
+        item_fill_map(&mut state);
+        state
+    }
+
+    fn run_systems(&mut self) {
+        death::system_kill_dead(self);
+        blockers::system_calc_blockers(self);
+        tile_contents::system_tile_contents(self);
+        viewer_look::system_calc_viewpoints(self);
+    }
+
+    /// Plays out everyone else's turns until the game needs a command
+    pub fn run_until_input(&mut self) {
+        while self.operating_mode == OperatingMode::Ticking {
+            self.run_systems(); // sometimes we get here direct from ui
+            let Some(turn) = self.turn_order.ready(&self.ecs) else {
+                if self.turn_order.is_empty() {
+                    break;
+                }
+                self.turn_order.new_round(&self.ecs);
+                timers::system_tick_timers(self);
+                terrain::system_terrain(self);
+                continue;
+            };
+            if status::has_status(&self.ecs, turn, status::StatusKind::Stun) {
+                self.turn_order.spend(turn, ACTION_COST);
+            } else if self.ecs.satisfies::<&Player>(turn).unwrap() {
+                self.operating_mode = OperatingMode::WaitingForInput;
+            } else if self.ecs.satisfies::<&Monster>(turn).unwrap() {
+                let cost = monster_act(self, turn);
+                self.turn_order.spend(turn, cost);
+                self.run_systems();
+            } else {
+                panic!("Non-actor in the actor queue");
+            }
+        }
+    }
+
+    /// Hands one command to whatever is waiting for it, then plays out turns until the next command is needed.
+    /// This is all `tick` does besides reading keys and rendering, so it can be driven without a window.
+    pub fn step(&mut self, command: Command) {
+        self.run_until_input();
+        if let Some(recorder) = &mut self.recorder {
+            // menu commands happen before the run starts, so replays leave them out
+            if !matches!(self.operating_mode, OperatingMode::MainMenu(_)) {
+                recorder.record(&command);
+            }
+        }
+        match &self.operating_mode {
+            OperatingMode::Ticking => {}
+            OperatingMode::WaitingForInput => {
+                self.noises.clear();
+                self.shots.clear();
+                if let Some(cost) = player::player_act(self, &command) {
+                    self.turn_order.spend(self.player_entity, cost);
+                    if self.operating_mode == OperatingMode::WaitingForInput {
+                        // if unchanged
+                        self.operating_mode = OperatingMode::Ticking;
+                    }
+                }
+            }
+            OperatingMode::OpenInventory(s) => {
+                let (ret, s) = ui::update_inventory_ui(s.clone(), self, command);
+                if let Some(ret) = ret {
+                    match ret {
+                        ui::InvUIRes::Select(idx) => {
+                            let inv = self
+                                .ecs
+                                .query_one_mut::<&Inventory>(self.player_entity)
+                                .unwrap();
+                            let item = inv.contents[idx as usize];
+                            if let Ok((bp, name)) =
+                                self.ecs.query_one_mut::<(&Blueprint, &Name)>(item)
+                            {
+                                self.messages
+                                    .enqueue_message(&format!("You attach the {}.", name.0));
+                                let bp = bp.clone();
+                                let (p, inv) = self
+                                    .ecs
+                                    .query_one_mut::<(&mut Player, &mut Inventory)>(
+                                        self.player_entity,
+                                    )
+                                    .unwrap();
+                                p.current_blueprint = Some(bp);
+                                inv.contents.remove(idx as usize);
+                                self.turn_order.spend(self.player_entity, ACTION_COST);
+                                self.operating_mode = OperatingMode::Ticking;
+                            } else {
+                                let name = self.ecs.query_one_mut::<&Name>(item).unwrap();
+                                self.messages.enqueue_message(&format!(
+                                    "Could not attach {}: it's not a artifact.",
+                                    name.0
+                                ));
+                                self.operating_mode = OperatingMode::Ticking;
+                            }
+                        }
+                        ui::InvUIRes::Done => {
+                            self.operating_mode = OperatingMode::Ticking;
+                        }
+                    }
+                } else {
+                    self.operating_mode = OperatingMode::OpenInventory(s);
+                }
+            }
+            OperatingMode::OpenMessageLog => {
+                if ui::update_message_log(command) {
+                    self.operating_mode = OperatingMode::Ticking;
+                }
+            }
+            OperatingMode::OpenExamine(s) => {
+                let (done, s) = ui::update_examine_ui(s.clone(), self, command);
+                if done == Some(ExamineUIRes::Done) {
+                    self.operating_mode = OperatingMode::Ticking;
+                } else {
+                    self.operating_mode = OperatingMode::OpenExamine(s);
+                }
+            }
+            OperatingMode::EquipmentTargetting {
+                state: s,
+                equipment,
+            } => {
+                let equipment = equipment.clone();
+                let (done, s) = ui::update_examine_ui(s.clone(), self, command);
+                match done {
+                    Some(ExamineUIRes::Done) => {
+                        self.operating_mode = OperatingMode::Ticking;
+                    }
+                    Some(ExamineUIRes::Select(pt)) => {
+                        self.noises.clear();
+                        let player_pos =
+                            self.ecs.query_one_mut::<&Position>(self.player_entity).unwrap().0;
+                        perception::make_noise(self, player_pos, perception::ARTIFACT_NOISE);
+                        execute_active_target(self, equipment, pt);
+                        self.turn_order.spend(self.player_entity, ACTION_COST);
+                        self.operating_mode = OperatingMode::Ticking;
+                    }
+                    None => {
+                        self.operating_mode = OperatingMode::EquipmentTargetting {
+                            state: s,
+                            equipment,
+                        };
+                    }
+                }
+            }
+            OperatingMode::MainMenu(s) => {
+                let (res, s) = update_main_menu(s.clone(), command);
+                match res {
+                    Some(MainMenuRes::NewGame) => match s.seed.parse::<u64>() {
+                        Ok(seed) if seed != self.seed => {
+                            let state = State::new_game(
+                                seed,
+                                GameOptions {
+                                    main_menu: false,
+                                    debug: self.debug,
+                                    record: self.recorder.is_some(),
+                                },
+                            );
+                            self.ecs.clear(); // just to be safe
+                            *self = state;
+                        }
+                        _ => {
+                            self.operating_mode = OperatingMode::Ticking;
+                        }
+                    },
+                    Some(MainMenuRes::Continue) => match save::load_from_file() {
+                        Ok(mut state) => {
+                            // debug mode is how the game was started, not part of the run
+                            state.debug = self.debug;
+                            if self.recorder.is_some() {
+                                // the new game's recording is no use, this picks up from the save instead
+                                let save = save::save_game(&state);
+                                state.recorder = replay::Recorder::create(
+                                    replay::REPLAY_PATH,
+                                    state.seed,
+                                    Some(save),
+                                )
+                                .ok();
+                            }
+                            self.ecs.clear(); // just to be safe
+                            *self = state;
+                        }
+                        Err(e) => {
+                            self.messages.enqueue_message(&format!(
+                                "Could not load the saved game: {}",
+                                e
+                            ));
+                            self.operating_mode = OperatingMode::Ticking;
+                        }
+                    },
+                    None => {
+                        self.operating_mode = OperatingMode::MainMenu(s);
+                    }
+                }
+            }
+            OperatingMode::GameOver => {
+                let done = ui::update_game_over(command);
+                if done {
+                    let state = State::new_game(
+                        random_seed(),
+                        GameOptions {
+                            main_menu: true,
+                            debug: self.debug,
+                            record: self.recorder.is_some(),
+                        },
+                    );
+                    self.ecs.clear(); // just to be safe
+                    *self = state;
+                }
+            }
+            OperatingMode::GameWon => {} // do nothing
+            OperatingMode::EquipmentExamining(s) => {
+                let (done, s) = ui::update_equip_examine(s.clone(), self, command);
+                if done {
+                    self.operating_mode = OperatingMode::Ticking;
+                } else {
+                    self.operating_mode = OperatingMode::EquipmentExamining(s);
+                }
+            }
+            OperatingMode::HelpMenu => {
+                let done = ui::update_help(command);
+                if done {
+                    self.operating_mode = OperatingMode::Ticking;
+                }
+            }
+        }
+        self.run_until_input();
+    }
+
+    fn render(&self, ctx: &mut BTerm) {
+        ctx.cls();
+        map::draw_map(self, ctx);
+        ui::draw_boss_bars(self, ctx);
+        ui::draw_messages(self, ctx);
+        ui::draw_side_info(self, ctx);
+        ui::draw_current_blueprint(self, ctx);
+        ui::draw_corners(ctx);
+        match &self.operating_mode {
+            OperatingMode::Ticking => {}
+            OperatingMode::WaitingForInput => {}
+            OperatingMode::OpenInventory(s) => ui::draw_inventory_ui(s, self, ctx),
+            OperatingMode::OpenMessageLog => ui::draw_message_log(self, ctx),
+            OperatingMode::OpenExamine(s) => ui::draw_examine_ui(s, self, ctx),
+            OperatingMode::EquipmentTargetting { state: s, .. } => {
+                ui::draw_examine_ui(s, self, ctx)
+            }
+            OperatingMode::MainMenu(s) => {
+                draw_main_menu(s, self, ctx);
+            }
+            OperatingMode::GameOver => {
+                ui::draw_game_over(self, ctx);
+            }
+            OperatingMode::GameWon => {
+                ui::draw_game_won(self, ctx);
+            }
+            OperatingMode::EquipmentExamining(s) => ui::draw_equip_examine(s, self, ctx),
+            OperatingMode::HelpMenu => ui::draw_help(ctx),
+        }
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut BTerm) {
+        if raws::reload_changed_raws() {
+            self.messages.enqueue_message("The raws have been reloaded.");
+        }
+        if !self.has_moved {
+            self.has_moved = true;
+            self.run_systems();
+            self.render(ctx);
+        }
+        self.run_until_input();
+        if let Some(playback) = &mut self.playback {
+            let mut due = vec![];
+            while let Some(command) = mapping::get_command(ctx) {
+                due.extend(playback.control(command));
+            }
+            due.extend(playback.advance(ctx.frame_time_ms));
+            for command in due {
+                self.step(command);
+            }
+            if self.playback.as_ref().map_or(false, |p| p.finished()) {
+                self.playback = None;
+                self.messages
+                    .enqueue_message("The replay is over, you have control.");
+            }
+        } else {
+            while let Some(command) = mapping::get_command(ctx) {
+                self.step(command);
+            }
+        }
+        self.render(ctx);
+    }
+}
@@ -1,10 +1,18 @@
 use std::collections::BTreeMap;
 
 use crate::{
-    components::*, monster::spawn_monster_idx, ui, OperatingMode, WINDOW_HEIGHT, WINDOW_WIDTH,
+    components::*,
+    equipment::element_color,
+    item::{pick_item, spawn_item},
+    map_builders::builder_for_depth,
+    monster::{pick_monster, spawn_monster, spawn_monster_idx},
+    raws::{BOSS_RANK, RAWS},
+    scheduler::MOVE_COST,
+    ui, WINDOW_HEIGHT, WINDOW_WIDTH,
 };
 use bracket_lib::prelude::*;
-use hecs::{Entity, Satisfies};
+use hecs::Entity;
+use serde::{Deserialize, Serialize};
 
 use crate::State;
 
@@ -15,23 +23,234 @@ pub const MAP_UI_DIM: Rect = Rect {
     y2: WINDOW_HEIGHT - ui::MESSAGE_LOG_HEIGHT,
 };
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Tile {
     Wall,
     Floor,
     Stairs,
+    /// back to the floor above, where the player arrives on every floor but the first
+    UpStairs,
+    Lava,
+    DeepWater,
+    ShallowWater,
+    /// blows whoever stands in it along
+    Wind(Direction),
+    Rubble,
+    Chasm,
+    Door {
+        open: bool,
+    },
+    /// a vent that burns whoever steps on it, only found in prefabs
+    Trap,
 }
 
+/// What a kind of tile is like, see `Tile::info`
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileInfo {
+    pub name: &'static str,
+    pub opaque: bool,
+    pub walkable: bool,
+    /// how many times as much as plain floor it costs to step onto
+    pub cost: f32,
+    /// burn damage per round to anything standing in it
+    pub damage: i32,
+    /// things of the same element aren't bothered by it
+    pub element: Option<Elemental>,
+    pub glyph: char,
+    pub fg: &'static str,
+    pub bg: &'static str,
+}
+
+const BLACK_BG: &str = "#000000";
+
+/// Plain floor, which the other tiles only say how they differ from
+const FLOOR: TileInfo = TileInfo {
+    name: "Floor",
+    opaque: false,
+    walkable: true,
+    cost: 1.0,
+    damage: 0,
+    element: None,
+    glyph: '.',
+    fg: "#f5f5dc",
+    bg: BLACK_BG,
+};
+
+impl Tile {
+    pub fn info(self) -> TileInfo {
+        use Elemental::*;
+        match self {
+            Tile::Wall => TileInfo {
+                name: "Wall",
+                opaque: true,
+                walkable: false,
+                glyph: '#',
+                fg: "#838b83",
+                ..FLOOR
+            },
+            Tile::Floor => FLOOR,
+            Tile::Stairs => TileInfo {
+                name: "Stairs",
+                glyph: '>',
+                fg: "#da2c43",
+                ..FLOOR
+            },
+            Tile::UpStairs => TileInfo {
+                name: "Up stairs",
+                glyph: '<',
+                fg: "#da2c43",
+                ..FLOOR
+            },
+            Tile::Lava => TileInfo {
+                name: "Lava",
+                damage: 2,
+                element: Some(Fire),
+                glyph: '~',
+                fg: "#ff4500",
+                bg: "#3a0a00",
+                ..FLOOR
+            },
+            Tile::DeepWater => TileInfo {
+                name: "Deep water",
+                cost: 2.0,
+                element: Some(Water),
+                glyph: '~',
+                fg: "#1e50c8",
+                bg: "#000a28",
+                ..FLOOR
+            },
+            Tile::ShallowWater => TileInfo {
+                name: "Shallow water",
+                cost: 1.5,
+                glyph: '~',
+                fg: "#5f9ea0",
+                ..FLOOR
+            },
+            Tile::Wind(dir) => TileInfo {
+                name: "Wind tunnel",
+                element: Some(Air),
+                glyph: dir.arrow(),
+                fg: "#b0e0e6",
+                ..FLOOR
+            },
+            Tile::Rubble => TileInfo {
+                name: "Rubble",
+                cost: 2.0,
+                glyph: ':',
+                fg: "#a0907d",
+                ..FLOOR
+            },
+            Tile::Chasm => TileInfo {
+                name: "Chasm",
+                walkable: false,
+                glyph: '░',
+                fg: "#3a3a5a",
+                ..FLOOR
+            },
+            Tile::Door { open: false } => TileInfo {
+                name: "Closed door",
+                opaque: true,
+                walkable: false,
+                glyph: '+',
+                fg: "#b5651d",
+                ..FLOOR
+            },
+            Tile::Door { open: true } => TileInfo {
+                name: "Open door",
+                glyph: '\'',
+                fg: "#b5651d",
+                ..FLOOR
+            },
+            Tile::Trap => TileInfo {
+                name: "Fire trap",
+                damage: 3,
+                element: Some(Fire),
+                glyph: '^',
+                fg: "#ff8c00",
+                ..FLOOR
+            },
+        }
+    }
+    pub fn walkable(self) -> bool {
+        self.info().walkable
+    }
+    /// Walkable, or will be once someone opens it
+    pub fn passable(self) -> bool {
+        self.walkable() || self.is_closed_door()
+    }
+    pub fn is_closed_door(self) -> bool {
+        self == Tile::Door { open: false }
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+    pub fn delta(self) -> Point {
+        match self {
+            Direction::North => Point::new(0, -1),
+            Direction::East => Point::new(1, 0),
+            Direction::South => Point::new(0, 1),
+            Direction::West => Point::new(-1, 0),
+        }
+    }
+    pub fn arrow(self) -> char {
+        match self {
+            Direction::North => '↑',
+            Direction::East => '→',
+            Direction::South => '↓',
+            Direction::West => '←',
+        }
+    }
+}
+
+/// Something a prefab asks to have put in a particular spot
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Spawn {
+    /// whatever a random monster on this depth would be
+    Monster,
+    Item,
+    /// one of the final bosses, by its index in the raws
+    Boss(usize),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Map {
     pub depth: i32,
     pub tiles: Vec<Tile>,
     pub width: i32,
     pub height: i32,
+    /// spawn regions, the first one holds `start`
     pub rooms: Vec<Rect>,
+    /// where the player arrives
+    #[serde(default = "Point::zero")]
+    pub start: Point,
+    /// the element the floor is aligned with, which its monsters and terrain lean towards
+    #[serde(default)]
+    pub element: Option<Elemental>,
+    /// placed by prefabs, used up when the floor is populated
+    #[serde(default)]
+    pub spawns: Vec<(Point, Spawn)>,
     pub visible_tiles: Vec<bool>,
     pub revealed_tiles: Vec<bool>,
     pub blocked_tiles: Vec<bool>,
     pub tile_contents: Vec<Vec<Entity>>,
+    /// goes up whenever the ground changes or something other than a monster starts or stops blocking, so
+    /// anything worked out from the layout (the `FlowField`) knows to redo it
+    #[serde(skip)]
+    pub changes: u64,
 }
 
 impl Algorithm2D for Map {
@@ -51,15 +270,16 @@ impl Algorithm2D for Map {
 
 impl BaseMap for Map {
     fn is_opaque(&self, i: usize) -> bool {
-        self.tiles[i] == Tile::Wall
+        self.tiles[i].info().opaque
     }
     fn get_available_exits(&self, i: usize) -> SmallVec<[(usize, f32); 10]> {
         let mut vec = SmallVec::<[(usize, f32); 10]>::new();
         let Point { x, y } = self.index_to_point2d(i);
         for (new_x, new_y) in [(x - 1, y), (x, y - 1), (x + 1, y), (x, y + 1)] {
-            if self.is_available_exit(self.point2d_to_index(Point::new(new_x, new_y))) {
-                let cost = 1.0;
-                vec.push((self.point2d_to_index(Point::new(new_x, new_y)), cost));
+            let idx = self.point2d_to_index(Point::new(new_x, new_y));
+            if self.is_available_exit(idx) {
+                let cost = 1.0 * self.tiles[idx].info().cost;
+                vec.push((idx, cost));
             }
         }
         for (new_x, new_y) in [
@@ -68,9 +288,10 @@ impl BaseMap for Map {
             (x + 1, y - 1),
             (x + 1, y + 1),
         ] {
-            if self.is_available_exit(self.point2d_to_index(Point::new(new_x, new_y))) {
-                let cost = 1.4;
-                vec.push((self.point2d_to_index(Point::new(new_x, new_y)), cost));
+            let idx = self.point2d_to_index(Point::new(new_x, new_y));
+            if self.is_available_exit(idx) {
+                let cost = 1.4 * self.tiles[idx].info().cost;
+                vec.push((idx, cost));
             }
         }
         vec
@@ -84,247 +305,187 @@ impl BaseMap for Map {
 }
 
 impl Map {
-    pub fn new(dl: i32, rng: &mut RandomNumberGenerator) -> Map {
-        const WIDTH: i32 = 70;
-        const HEIGHT: i32 = 60;
-        const SIZE: usize = (WIDTH * HEIGHT) as usize;
-        let mut map = Map {
-            depth: dl,
-            tiles: vec![Tile::Wall; SIZE],
+    /// Solid wall, for generators to carve into
+    pub fn blank(depth: i32, width: i32, height: i32) -> Map {
+        let size = (width * height) as usize;
+        Map {
+            depth,
+            tiles: vec![Tile::Wall; size],
             rooms: Vec::new(),
-            width: WIDTH,
-            height: HEIGHT,
-            visible_tiles: vec![false; SIZE],
-            revealed_tiles: vec![false; SIZE],
-            blocked_tiles: vec![false; SIZE],
-            tile_contents: vec![Vec::new(); SIZE],
-        };
-
-        let mut rooms = Vec::<Rect>::new();
-
-        for _i in 0..15 {
-            let new_room = loop {
-                let room_center = (rng.range(10, WIDTH - 10), rng.range(10, HEIGHT - 10));
-                let width = rng.range(5, 9);
-                let height = rng.range(5, 9);
-                let room = Rect::with_size(
-                    room_center.0 - width / 2,
-                    room_center.1 - height / 2,
-                    width,
-                    height,
-                );
-
-                if !rooms.iter().any(|r| room.intersect(r)) {
-                    break room;
-                }
-            };
-            rooms.push(new_room);
-        }
-
-        #[derive(Clone, Copy)]
-        enum Dir {
-            Horizontal,
-            Vertical,
-        }
-        let mut halls = Vec::<(Point, Dir, i32)>::new();
-
-        // connect each room to the next
-        for i in 0..rooms.len() - 1 {
-            let start = rooms[i].center();
-            let end = rooms[i + 1].center();
-            let diff = end - start;
-            let hall_horizontal = (start, Dir::Horizontal, diff.x);
-            let hall_vertical = (start + Point::new(diff.x, 0), Dir::Vertical, diff.y);
-            halls.push(hall_horizontal);
-            halls.push(hall_vertical);
-        }
-
-        // carve out rooms
-        for room in rooms.iter() {
-            room.for_each(|p| {
-                let i = map.point2d_to_index(p);
-                map.tiles[i] = Tile::Floor;
-            });
+            start: Point::zero(),
+            element: None,
+            spawns: Vec::new(),
+            width,
+            height,
+            visible_tiles: vec![false; size],
+            revealed_tiles: vec![false; size],
+            blocked_tiles: vec![false; size],
+            tile_contents: vec![Vec::new(); size],
+            changes: 0,
         }
-
-        map.rooms = rooms;
-
-        // carve out halls
-        for (base, dir, len) in halls {
-            let range = if len < 0 { len..=0 } else { 0..=len };
-            for i in range {
-                match dir {
-                    Dir::Horizontal => {
-                        let i = map.point2d_to_index(base + Point::new(i, 0));
-                        map.tiles[i] = Tile::Floor;
-                    }
-                    Dir::Vertical => {
-                        let i = map.point2d_to_index(base + Point::new(0, i));
-                        map.tiles[i] = Tile::Floor;
-                    }
-                }
-            }
-        }
-
-        let idx = map.point2d_to_index(random_room_point(&map, rng));
-        map.tiles[idx] = Tile::Stairs;
-        map
     }
 
-    pub fn make_last_room(_rng: &mut RandomNumberGenerator) -> Map {
-        const WIDTH: i32 = 50;
-        const HEIGHT: i32 = 20;
-        const SIZE: usize = (WIDTH * HEIGHT) as usize;
-        let mut map = Map {
-            depth: 5,
-            tiles: vec![Tile::Wall; SIZE],
-            rooms: Vec::new(),
-            width: WIDTH,
-            height: HEIGHT,
-            visible_tiles: vec![false; SIZE],
-            revealed_tiles: vec![false; SIZE],
-            blocked_tiles: vec![false; SIZE],
-            tile_contents: vec![Vec::new(); SIZE],
-        };
-
-        let mut rooms = vec![];
-        let starting_room = Rect::with_size(7, 7, 6, 6);
-        rooms.push(starting_room);
-
-        let grand_room = Rect::with_size(20, 5, 15, 10);
-        rooms.push(grand_room);
-
-        for x in 10..30 {
-            let idx1 = map.point2d_to_index(Point::new(x, HEIGHT / 2));
-            let idx2 = map.point2d_to_index(Point::new(x, HEIGHT / 2 - 1));
-            map.tiles[idx1] = Tile::Floor;
-            map.tiles[idx2] = Tile::Floor;
-        }
-
-        // carve out rooms
-        for room in rooms.iter() {
-            room.for_each(|p| {
-                let i = map.point2d_to_index(p);
-                map.tiles[i] = Tile::Floor;
-            });
-        }
-
-        let idx = map.point2d_to_index(Point::new(35, 5));
-        map.tiles[idx] = Tile::Floor;
-        let idx = map.point2d_to_index(Point::new(36, 5));
-        map.tiles[idx] = Tile::Stairs;
-
-        map.rooms = rooms;
+    pub fn new(dl: i32, rng: &mut RandomNumberGenerator) -> Map {
+        builder_for_depth(dl, rng).build(rng)
+    }
 
-        map
+    /// Changes a tile once the floor is in play
+    pub fn set_tile(&mut self, idx: usize, tile: Tile) {
+        self.tiles[idx] = tile;
+        self.changes += 1;
     }
 
     pub fn is_available_exit(&self, i: usize) -> bool {
         self.in_bounds(self.index_to_point2d(i)) && !self.blocked_tiles[i]
     }
+
+    /// Energy it takes to step onto a tile
+    pub fn move_cost(&self, i: usize) -> i32 {
+        (MOVE_COST as f32 * self.tiles[i].info().cost) as i32
+    }
+}
+
+/// Each floor gets a generator of its own, so its layout and spawns only depend on the run seed and not on what
+/// happened on the floors above
+pub fn floor_rng(seed: u64, depth: i32) -> RandomNumberGenerator {
+    RandomNumberGenerator::seeded(seed ^ (depth as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))
 }
 
-pub fn random_room_point(map: &Map, rng: &mut RandomNumberGenerator) -> Point {
+pub fn random_room_point(map: &Map, rng: &mut RandomNumberGenerator) -> Option<Point> {
     let room_idx = rng.range(0, map.rooms.len());
-    let room = map.rooms[room_idx];
-    debug_assert!(room.x1 <= room.x2);
-    debug_assert!(room.y1 <= room.y2);
-    let x = rng.range(room.x1, room.x2);
-    let y = rng.range(room.y1, room.y2);
-    Point::new(x, y)
+    random_point_in_room(map, map.rooms[room_idx], rng)
+}
+
+pub fn is_safe_spot(tile: Tile) -> bool {
+    let info = tile.info();
+    info.walkable && info.damage == 0
 }
 
-pub fn populate_map_last_level(state: &mut State) {
-    let fire = spawn_monster_idx(
-        state,
-        666,
-        state.map.rooms[1].center() + Point::new(0, -4),
-        0,
-    );
-    let air = spawn_monster_idx(state, 666, state.map.rooms[1].center(), 1);
-    let water = spawn_monster_idx(
-        state,
-        666,
-        state.map.rooms[1].center() + Point::new(0, 4),
-        2,
-    );
-    state.turn_order.push_back(fire);
-    state.turn_order.push_back(air);
-    state.turn_order.push_back(water);
+/// Rooms cut out of caves aren't all floor, and terrain can cover whole rooms, so this picks among the spots in
+/// it that can be stood on without getting hurt. `None` if there aren't any.
+pub fn random_point_in_room(
+    map: &Map,
+    room: Rect,
+    rng: &mut RandomNumberGenerator,
+) -> Option<Point> {
+    debug_assert!(room.x1 < room.x2);
+    debug_assert!(room.y1 < room.y2);
+    let mut spots = vec![];
+    room.for_each(|pt| {
+        if map.in_bounds(pt) && is_safe_spot(map.tiles[map.point2d_to_index(pt)]) {
+            spots.push(pt);
+        }
+    });
+    if spots.is_empty() {
+        return None;
+    }
+    Some(spots[rng.range(0, spots.len())])
 }
 
+/// Takes out the spawns prefabs asked for of one kind
+fn take_spawns(state: &mut State, keep: impl Fn(Spawn) -> bool) -> Vec<(Point, Spawn)> {
+    let (taken, rest): (Vec<_>, Vec<_>) = std::mem::take(&mut state.map.spawns)
+        .into_iter()
+        .partition(|(_, spawn)| keep(*spawn));
+    state.map.spawns = rest;
+    taken
+}
+
+/// How many times to roll for a free spot before giving up on filling a floor, small floors can run out of them
+const SPAWN_ATTEMPTS: usize = 1000;
+
 pub fn populate_map(state: &mut State) {
-    if state.map.depth == 5 {
-        populate_map_last_level(state);
-        return;
+    for (pt, spawn) in take_spawns(state, |s| matches!(s, Spawn::Boss(_))) {
+        let Spawn::Boss(idx) = spawn else { continue };
+        let entity = spawn_monster_idx(state, BOSS_RANK, pt, idx);
+        state.turn_order.add(entity);
     }
-    let mut new_monsters: Vec<Point> = Vec::new();
-    let mut count = 0;
-    loop {
-        let pt = random_room_point(&state.map, &mut state.rng);
-        if state.map.depth == 0 && state.map.rooms[0].point_in_rect(pt) {
+    // spots prefabs asked for get a monster each on top of the usual number
+    for (pt, _) in take_spawns(state, |s| s == Spawn::Monster) {
+        let Some(entry) = pick_monster(state) else { break };
+        let entity = spawn_monster(state, &entry, pt);
+        state.turn_order.add(entity);
+    }
+    let count = RAWS.lock().unwrap().table.counts(state.map.depth).monsters;
+    let mut taken: Vec<Point> = vec![];
+    for _ in 0..SPAWN_ATTEMPTS {
+        if taken.len() >= count {
+            break;
+        }
+        let Some(entry) = pick_monster(state) else { break };
+        let Some(leader) = random_room_point(&state.map, &mut state.rng) else { break };
+        if state.map.depth == 0 && state.map.rooms[0].point_in_rect(leader) {
             // avoid spawning monsters in starting room
             continue;
         }
-        if !new_monsters.contains(&pt) {
-            new_monsters.push(pt);
-            count += 1;
-            if count >= 15 {
-                break;
-            }
+        if taken.contains(&leader) {
+            continue;
         }
-    }
-    for pt in new_monsters {
-        let edl;
-        if state.map.depth > 4 {
-            edl = 2;
-        } else if state.map.depth % 2 == 1 {
-            edl = state.map.depth / 2 + state.rng.range(0, 2);
-        } else {
-            edl = state.map.depth / 2;
+        let size = entry.group_size(&mut state.rng).min(count - taken.len());
+        for pt in group_points(&state.map, leader, size, &taken) {
+            taken.push(pt);
+            let entity = spawn_monster(state, &entry, pt);
+            state.turn_order.add(entity);
         }
-        let entity = crate::monster::spawn_monster(state, edl, pt);
-        state.turn_order.push_back(entity);
     }
 }
 
 pub fn item_fill_map(state: &mut State) {
-    if state.map.depth == 5 {
-        return;
-    }
-    let mut new_items: Vec<Point> = Vec::new();
-    let mut count = 0;
+    let mut spots: Vec<Point> = take_spawns(state, |s| s == Spawn::Item)
+        .into_iter()
+        .map(|(pt, _)| pt)
+        .collect();
     if state.map.depth == 0 {
         // guarantee a item in the starting room
-        let room = state.map.rooms[0];
-        debug_assert!(room.x1 <= room.x2);
-        debug_assert!(room.y1 <= room.y2);
-        let item_x = state.rng.range(room.x1, room.x2);
-        let item_y = state.rng.range(room.y1, room.y2);
-        let pt = Point::new(item_x, item_y);
-        new_items.push(pt);
+        if let Some(pt) = random_point_in_room(&state.map, state.map.rooms[0], &mut state.rng) {
+            spots.push(pt);
+        }
     }
-    loop {
-        let pt = random_room_point(&state.map, &mut state.rng);
-        if !new_items.contains(&pt) {
-            new_items.push(pt);
-            count += 1;
-            if count >= 3 {
-                break;
-            }
+    for pt in spots.iter() {
+        let Some(entry) = pick_item(state) else { break };
+        spawn_item(state, &entry, *pt);
+    }
+    let count = RAWS.lock().unwrap().table.counts(state.map.depth).items;
+    let mut taken: Vec<Point> = vec![];
+    for _ in 0..SPAWN_ATTEMPTS {
+        if taken.len() >= count {
+            break;
+        }
+        let Some(entry) = pick_item(state) else { break };
+        let Some(leader) = random_room_point(&state.map, &mut state.rng) else { break };
+        if taken.contains(&leader) || spots.contains(&leader) {
+            continue;
+        }
+        let size = entry.group_size(&mut state.rng).min(count - taken.len());
+        for pt in group_points(&state.map, leader, size, &taken) {
+            taken.push(pt);
+            spawn_item(state, &entry, pt);
         }
     }
-    for pt in new_items {
-        let edl;
-        if state.map.depth > 4 {
-            edl = 2;
-        } else if state.map.depth % 2 == 1 {
-            edl = state.map.depth / 2 + state.rng.range(0, 2);
-        } else {
-            edl = state.map.depth / 2;
+}
+
+/// Up to `size` safe spots nearest `leader`, starting with it, that aren't already `taken`
+fn group_points(map: &Map, leader: Point, size: usize, taken: &[Point]) -> Vec<Point> {
+    let mut group = vec![leader];
+    let mut open = std::collections::VecDeque::from([leader]);
+    while let Some(pt) = open.pop_front() {
+        for dir in Direction::ALL {
+            if group.len() >= size {
+                return group;
+            }
+            let next = pt + dir.delta();
+            if !map.in_bounds(next)
+                || !is_safe_spot(map.tiles[map.point2d_to_index(next)])
+                || group.contains(&next)
+                || taken.contains(&next)
+            {
+                continue;
+            }
+            group.push(next);
+            open.push_back(next);
         }
-        crate::item::spawn_item(state, edl, pt);
     }
+    group
 }
 
 pub fn draw_map(state: &State, ctx: &mut BTerm) {
@@ -341,42 +502,71 @@ pub fn draw_map(state: &State, ctx: &mut BTerm) {
         let idx = state.map.point2d_to_index(pt + offset);
         if let Some(tile) = state.map.tiles.get(idx) {
             if state.map.revealed_tiles[idx] {
-                let glyph;
-                let mut fg;
-                match tile {
-                    Tile::Floor => {
-                        glyph = to_cp437('.');
-                        fg = RGB::from_hex("#f5f5dc").unwrap();
-                    }
-                    Tile::Wall => {
-                        glyph = to_cp437('#');
-                        if state.map.depth == 5 {
-                            fg = RGB::from_hex("#8a2be2").unwrap();
-                        } else {
-                            fg = RGB::from_hex("#838b83").unwrap();
-                        }
-                    }
-                    Tile::Stairs => {
-                        glyph = to_cp437('>');
-                        fg = RGB::from_hex("#da2c43").unwrap();
-                    }
-                }
+                let info = tile.info();
+                let fg_hex = match (tile, state.map.depth, &state.map.element) {
+                    // walls take on the floor's colours
+                    (Tile::Wall, 5, _) => "#8a2be2",
+                    (Tile::Wall, _, Some(Elemental::Fire)) => "#8b6b5e",
+                    (Tile::Wall, _, Some(Elemental::Water)) => "#6b7b8b",
+                    (Tile::Wall, _, Some(Elemental::Air)) => "#9ca39c",
+                    _ => info.fg,
+                };
+                let mut fg = RGB::from_hex(fg_hex).unwrap();
+                let mut bg = RGB::from_hex(info.bg).unwrap();
                 if !state.map.visible_tiles[idx] {
                     fg = fg.to_greyscale();
+                    bg = bg.to_greyscale();
                 }
-                ctx.set(pt.x, pt.y, fg, RGB::from_f32(0., 0., 0.), glyph);
+                ctx.set(pt.x, pt.y, fg, bg, to_cp437(info.glyph));
             }
         }
     });
 
-    draw_entities(state, ctx, offset)
+    draw_entities(state, ctx, offset);
+    draw_telegraphs(state, ctx, offset);
+    draw_shots(state, ctx, offset);
+}
+
+/// Where bosses are about to hit, a turn ahead so the player can get out of the way
+fn draw_telegraphs(state: &State, ctx: &mut BTerm, offset: Point) {
+    for (_, boss) in state.ecs.query::<&Boss>().iter() {
+        let color = element_color(&boss.attack.element()) * 0.5;
+        for pt in boss.telegraph.iter() {
+            let pos = *pt - offset;
+            let idx = state.map.point2d_to_index(*pt);
+            if MAP_UI_DIM.point_in_rect(pos) && state.map.visible_tiles[idx] {
+                ctx.set_bg(pos.x, pos.y, color);
+            }
+        }
+    }
+}
+
+/// Shots go under anything standing in the way, so nobody is hidden by them; whoever's there just gets tinted
+fn draw_shots(state: &State, ctx: &mut BTerm, offset: Point) {
+    for shot in state.shots.iter() {
+        for pt in shot.tiles.iter() {
+            let pos = *pt - offset;
+            if !MAP_UI_DIM.point_in_rect(pos) || !state.map.in_bounds(*pt) {
+                continue;
+            }
+            let idx = state.map.point2d_to_index(*pt);
+            if !state.map.visible_tiles[idx] {
+                continue;
+            }
+            if state.map.tile_contents[idx].is_empty() {
+                ctx.set(pos.x, pos.y, shot.fg, RGB::named(BLACK), to_cp437(shot.glyph));
+            } else {
+                ctx.set_bg(pos.x, pos.y, shot.fg * 0.5);
+            }
+        }
+    }
 }
 
 fn draw_entities(state: &State, ctx: &mut BTerm, offset: Point) {
     let mut renderings: BTreeMap<i32, Vec<(Renderable, Point)>> = BTreeMap::new();
-    for (_id, (pos, render, slowed)) in state
+    for (_id, (pos, render, statuses)) in state
         .ecs
-        .query::<(&Position, &Renderable, Satisfies<&Slowed>)>()
+        .query::<(&Position, &Renderable, Option<&StatusEffects>)>()
         .iter()
     {
         if state.map.visible_tiles[state.map.point2d_to_index(pos.0)] {
@@ -384,8 +574,8 @@ fn draw_entities(state: &State, ctx: &mut BTerm, offset: Point) {
                 renderings.insert(render.layer, Vec::new());
             }
             let mut drawing = render.clone();
-            if slowed {
-                drawing.bg = RGB::named(DARKBLUE);
+            if let Some(status) = statuses.and_then(|s| s.0.first()) {
+                drawing.bg = status.kind.color();
             }
             renderings
                 .get_mut(&render.layer)
@@ -403,34 +593,3 @@ fn draw_entities(state: &State, ctx: &mut BTerm, offset: Point) {
     }
 }
 
-pub fn new_floor(state: &mut State) {
-    let new_map;
-    if state.map.depth == 5 {
-        // we beat the game!!
-        state.operating_mode = OperatingMode::GameWon;
-        return;
-    } else if state.map.depth == 4 {
-        new_map = Map::make_last_room(&mut state.rng);
-    } else {
-        new_map = Map::new(state.map.depth + 1, &mut state.rng);
-    }
-    state.map = new_map;
-    let (position, viewer, health) = state
-        .ecs
-        .query_one_mut::<(&mut Position, &mut Viewer, &mut Health)>(state.player_entity)
-        .unwrap();
-    position.0 = state.map.rooms[0].center();
-    viewer.dirty = true;
-    health.hp = health.max_hp;
-    let mut gone = vec![];
-    for (ent, _i) in state.ecs.query_mut::<&Ephermal>() {
-        gone.push(ent);
-    }
-    for ent in gone {
-        state.ecs.despawn(ent).unwrap();
-    }
-    state.turn_order.clear();
-    state.turn_order.push_back(state.player_entity);
-    populate_map(state);
-    item_fill_map(state);
-}
@@ -0,0 +1,367 @@
+//! Level generators. A level is made by running a chain of builders over the same `BuildData`: the first lays the
+//! whole floor out, any after it (like vaults) carve things into what's there. Which chains each depth can use is
+//! data in `raws/levels.json`.
+//!
+//! Once the chain is done, anything that can't be walked to from the start is filled in, the stairs are placed (with
+//! the way back up at the start, below the first floor) and
+//! `Map::rooms` is left holding the spawn regions, with the start in the first one.
+
+use bracket_lib::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    components::Elemental,
+    map::{is_safe_spot, Map, Spawn, Tile},
+    raws::RAWS,
+};
+
+pub mod bsp;
+pub mod caves;
+pub mod doors;
+pub mod drunkard;
+pub mod prefab;
+pub mod rooms;
+pub mod terrain;
+pub mod vault;
+
+pub const MAP_WIDTH: i32 = 70;
+pub const MAP_HEIGHT: i32 = 60;
+
+pub struct BuildData {
+    pub map: Map,
+    /// where the player arrives, the center of the first spawn region if left empty
+    pub start: Option<Point>,
+    /// as far from the start as the level goes if left empty
+    pub stairs: Option<Point>,
+    pub element: Option<Elemental>,
+    /// things prefabs asked for, dropped if they end up somewhere that can't be walked to
+    pub spawns: Vec<(Point, Spawn)>,
+}
+
+pub trait MapBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator);
+}
+
+/// The builders as they're written in `levels.json`
+#[derive(Clone, Debug, Deserialize)]
+pub enum BuilderSpec {
+    Rooms,
+    Bsp,
+    Caves,
+    DrunkardsWalk,
+    /// a whole level from the prefab with this name in `prefabs.json`
+    Prefab(String),
+    /// prefabs allowed on the depth, each dug in with its own chance
+    Vaults,
+    /// ground matching the floor's element
+    Terrain,
+    /// chance out of 100 of each doorway into a room getting a door
+    Doors(i32),
+}
+
+impl BuilderSpec {
+    pub fn builder(&self) -> Box<dyn MapBuilder> {
+        match self {
+            BuilderSpec::Rooms => Box::new(rooms::RoomsBuilder::default()),
+            BuilderSpec::Bsp => Box::new(bsp::BspBuilder::default()),
+            BuilderSpec::Caves => Box::new(caves::CaveBuilder::default()),
+            BuilderSpec::DrunkardsWalk => Box::new(drunkard::DrunkardBuilder::default()),
+            BuilderSpec::Prefab(name) => Box::new(prefab::PrefabBuilder { name: name.clone() }),
+            BuilderSpec::Vaults => Box::new(vault::VaultBuilder),
+            BuilderSpec::Terrain => Box::new(terrain::TerrainBuilder::default()),
+            BuilderSpec::Doors(chance) => Box::new(doors::DoorBuilder { chance: *chance }),
+        }
+    }
+}
+
+fn default_weight() -> i32 {
+    1
+}
+
+/// One way a depth can be laid out
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelLayout {
+    #[serde(default = "default_weight")]
+    pub weight: i32,
+    pub builders: Vec<BuilderSpec>,
+}
+
+/// Everything `levels.json` says about one depth
+#[derive(Clone, Debug, Deserialize)]
+pub struct LevelDef {
+    #[serde(default)]
+    pub element: Option<Elemental>,
+    pub layouts: Vec<LevelLayout>,
+}
+
+pub struct BuilderChain {
+    depth: i32,
+    element: Option<Elemental>,
+    builders: Vec<Box<dyn MapBuilder>>,
+}
+
+impl BuilderChain {
+    pub fn new(depth: i32, element: Option<Elemental>) -> BuilderChain {
+        BuilderChain {
+            depth,
+            element,
+            builders: vec![],
+        }
+    }
+
+    pub fn with(mut self, builder: Box<dyn MapBuilder>) -> BuilderChain {
+        self.builders.push(builder);
+        self
+    }
+
+    pub fn from_layout(depth: i32, element: Option<Elemental>, layout: &LevelLayout) -> BuilderChain {
+        layout
+            .builders
+            .iter()
+            .fold(BuilderChain::new(depth, element), |chain, spec| {
+                chain.with(spec.builder())
+            })
+    }
+
+    pub fn build(&mut self, rng: &mut RandomNumberGenerator) -> Map {
+        let mut data = BuildData {
+            map: Map::blank(self.depth, MAP_WIDTH, MAP_HEIGHT),
+            start: None,
+            stairs: None,
+            element: self.element.clone(),
+            spawns: vec![],
+        };
+        for builder in self.builders.iter_mut() {
+            builder.build(&mut data, rng);
+        }
+        finish(data)
+    }
+}
+
+/// Picks one of the layouts `levels.json` has for `depth`, plain rooms if it has none
+pub fn builder_for_depth(depth: i32, rng: &mut RandomNumberGenerator) -> BuilderChain {
+    let raws = RAWS.lock().unwrap();
+    let Some(def) = raws.levels.get(&depth) else {
+        return BuilderChain::new(depth, None).with(Box::new(rooms::RoomsBuilder::default()));
+    };
+    let total: i32 = def.layouts.iter().map(|l| l.weight).sum();
+    if total <= 0 {
+        return BuilderChain::new(depth, def.element.clone())
+            .with(Box::new(rooms::RoomsBuilder::default()));
+    }
+    let mut roll = rng.range(0, total);
+    for layout in def.layouts.iter() {
+        if roll < layout.weight {
+            return BuilderChain::from_layout(depth, def.element.clone(), layout);
+        }
+        roll -= layout.weight;
+    }
+    unreachable!()
+}
+
+fn finish(data: BuildData) -> Map {
+    let BuildData {
+        mut map,
+        start,
+        stairs,
+        element,
+        spawns,
+    } = data;
+    let center = Point::new(map.width / 2, map.height / 2);
+    let start = start
+        .or_else(|| map.rooms.first().map(|r| r.center()))
+        .filter(|pt| map.in_bounds(*pt) && map.tiles[map.point2d_to_index(*pt)].walkable())
+        .or_else(|| nearest_floor(&map, center))
+        .expect("level has no floor");
+    // stairs are often put in the wall at the end of a hall, so they have to be there before working out what's
+    // reachable
+    if let Some(pt) = stairs.filter(|pt| map.in_bounds(*pt) && *pt != start) {
+        let idx = map.point2d_to_index(pt);
+        map.tiles[idx] = Tile::Stairs;
+    }
+    remove_unreachable(&mut map, start);
+
+    let distances = walk_distances(&map, start);
+    let reachable = |pt: &Point| {
+        *pt != start && map.in_bounds(*pt) && distances[map.point2d_to_index(*pt)].is_some()
+    };
+    let stairs = stairs.filter(reachable).unwrap_or_else(|| {
+        let (idx, _) = distances
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, dist)| dist.map(|d| (idx, d)))
+            .max_by_key(|(_, d)| *d)
+            .unwrap();
+        map.index_to_point2d(idx)
+    });
+    let spawns: Vec<(Point, Spawn)> = spawns
+        .into_iter()
+        .filter(|(pt, _)| reachable(pt) && is_safe_spot(map.tiles[map.point2d_to_index(*pt)]))
+        .collect();
+    let idx = map.point2d_to_index(stairs);
+    map.tiles[idx] = Tile::Stairs;
+    if map.depth > 0 {
+        let idx = map.point2d_to_index(start);
+        map.tiles[idx] = Tile::UpStairs;
+    }
+    map.spawns = spawns;
+
+    let mut rooms = std::mem::take(&mut map.rooms);
+    // terrain can cover a room whole, and there'd be nowhere in it to put anything
+    rooms.retain(|room| {
+        let mut open = false;
+        room.for_each(|pt| {
+            open |= map.in_bounds(pt) && is_safe_spot(map.tiles[map.point2d_to_index(pt)])
+        });
+        open
+    });
+    if !rooms.first().map_or(false, |r| r.point_in_rect(start)) {
+        rooms.insert(0, Rect::with_size(start.x - 2, start.y - 2, 5, 5));
+    }
+    map.rooms = rooms;
+    map.start = start;
+    map.element = element;
+    map
+}
+
+pub fn nearest_floor(map: &Map, pt: Point) -> Option<Point> {
+    map.tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == Tile::Floor)
+        .map(|(idx, _)| map.index_to_point2d(idx))
+        .min_by_key(|p| (p.x - pt.x).pow(2) + (p.y - pt.y).pow(2))
+}
+
+/// Digs an L-shaped hall, across from `start` then down or up to `end`
+pub fn dig_hall(map: &mut Map, start: Point, end: Point) {
+    let (x1, x2) = (start.x.min(end.x), start.x.max(end.x));
+    for x in x1..=x2 {
+        let idx = map.point2d_to_index(Point::new(x, start.y));
+        map.tiles[idx] = Tile::Floor;
+    }
+    let (y1, y2) = (start.y.min(end.y), start.y.max(end.y));
+    for y in y1..=y2 {
+        let idx = map.point2d_to_index(Point::new(end.x, y));
+        map.tiles[idx] = Tile::Floor;
+    }
+}
+
+pub fn dig_room(map: &mut Map, room: &Rect) {
+    room.for_each(|p| {
+        let i = map.point2d_to_index(p);
+        map.tiles[i] = Tile::Floor;
+    });
+}
+
+/// How far every tile is from `start`, in steps, going through doors
+pub fn walk_distances(map: &Map, start: Point) -> Vec<Option<i32>> {
+    let mut distances = vec![None; map.tiles.len()];
+    let start_idx = map.point2d_to_index(start);
+    distances[start_idx] = Some(0);
+    let mut open = std::collections::VecDeque::from([start]);
+    while let Some(pt) = open.pop_front() {
+        let dist = distances[map.point2d_to_index(pt)].unwrap();
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let next = pt + Point::new(dx, dy);
+                if !map.in_bounds(next) {
+                    continue;
+                }
+                let idx = map.point2d_to_index(next);
+                if !map.tiles[idx].passable() || distances[idx].is_some() {
+                    continue;
+                }
+                distances[idx] = Some(dist + 1);
+                open.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Walls in everything that can't be walked to from `start`, returning how many tiles are left open. Things you
+/// couldn't walk on anyway, like chasms, are left be.
+pub fn remove_unreachable(map: &mut Map, start: Point) -> usize {
+    let distances = walk_distances(map, start);
+    let mut open = 0;
+    for (idx, dist) in distances.iter().enumerate() {
+        if dist.is_some() {
+            open += 1;
+        } else if map.tiles[idx].passable() {
+            map.tiles[idx] = Tile::Wall;
+        }
+    }
+    open
+}
+
+/// Splits the open parts of a map into spawn regions, one per `size` by `size` chunk that has at least `min_open`
+/// tiles in it that are safe to stand on. Each region is the bounding box of its chunk's safe tiles, so it can
+/// still hold some walls.
+pub fn spawn_regions(map: &Map, size: i32, min_open: usize) -> Vec<Rect> {
+    let mut regions = vec![];
+    for chunk_y in (0..map.height).step_by(size as usize) {
+        for chunk_x in (0..map.width).step_by(size as usize) {
+            let mut open = 0;
+            let mut bounds: Option<(Point, Point)> = None;
+            for y in chunk_y..(chunk_y + size).min(map.height) {
+                for x in chunk_x..(chunk_x + size).min(map.width) {
+                    let pt = Point::new(x, y);
+                    if !is_safe_spot(map.tiles[map.point2d_to_index(pt)]) {
+                        continue;
+                    }
+                    open += 1;
+                    bounds = Some(match bounds {
+                        None => (pt, pt),
+                        Some((min, max)) => (
+                            Point::new(min.x.min(x), min.y.min(y)),
+                            Point::new(max.x.max(x), max.y.max(y)),
+                        ),
+                    });
+                }
+            }
+            if let Some((min, max)) = bounds {
+                if open >= min_open {
+                    regions.push(Rect::with_exact(min.x, min.y, max.x + 1, max.y + 1));
+                }
+            }
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::map::random_point_in_room;
+
+    #[test]
+    fn every_layout_builds() {
+        RAWS.lock().unwrap().prefabs = crate::raws::load_prefabs();
+        let levels: HashMap<i32, LevelDef> =
+            serde_json::from_str(include_str!("../raws/levels.json")).unwrap();
+        for (depth, def) in levels.iter() {
+            for layout in def.layouts.iter() {
+                for seed in 0..3 {
+                    let mut rng = RandomNumberGenerator::seeded(seed);
+                    let map = BuilderChain::from_layout(*depth, def.element.clone(), layout)
+                        .build(&mut rng);
+                    assert_eq!(map.depth, *depth);
+                    assert!(map.rooms[0].point_in_rect(map.start), "{layout:?}");
+                    let distances = walk_distances(&map, map.start);
+                    let stairs = map.tiles.iter().position(|t| *t == Tile::Stairs).unwrap();
+                    assert!(distances[stairs].is_some(), "{layout:?}");
+                    for (idx, tile) in map.tiles.iter().enumerate() {
+                        assert_eq!(tile.passable(), distances[idx].is_some());
+                    }
+                    // nothing would get spawned in a room covered in lava
+                    for room in map.rooms.iter() {
+                        assert!(random_point_in_room(&map, *room, &mut rng).is_some());
+                    }
+                }
+            }
+        }
+    }
+}
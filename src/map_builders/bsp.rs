@@ -0,0 +1,98 @@
+//! Binary space partitioning: keep cutting the level in two until the pieces are small, put a room in each piece,
+//! then join the rooms up in the order the cuts made them, so neighbours end up connected.
+
+use bracket_lib::prelude::*;
+
+use super::{dig_hall, dig_room, BuildData, MapBuilder, MAP_HEIGHT, MAP_WIDTH};
+use crate::map::Map;
+
+#[derive(Clone, Debug)]
+pub struct BspBuilder {
+    /// pieces are never cut smaller than this on either side
+    pub min_leaf: i32,
+}
+
+impl Default for BspBuilder {
+    fn default() -> Self {
+        BspBuilder { min_leaf: 10 }
+    }
+}
+
+impl BspBuilder {
+    fn split(&self, area: Rect, rng: &mut RandomNumberGenerator, leaves: &mut Vec<Rect>) {
+        let can_split_x = area.width() >= self.min_leaf * 2;
+        let can_split_y = area.height() >= self.min_leaf * 2;
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => {
+                leaves.push(area);
+                return;
+            }
+            (true, true) => rng.range(0, 2) == 0,
+            (x, _) => x,
+        };
+        if split_x {
+            let cut = rng.range(area.x1 + self.min_leaf, area.x2 - self.min_leaf + 1);
+            self.split(Rect::with_exact(area.x1, area.y1, cut, area.y2), rng, leaves);
+            self.split(Rect::with_exact(cut, area.y1, area.x2, area.y2), rng, leaves);
+        } else {
+            let cut = rng.range(area.y1 + self.min_leaf, area.y2 - self.min_leaf + 1);
+            self.split(Rect::with_exact(area.x1, area.y1, area.x2, cut), rng, leaves);
+            self.split(Rect::with_exact(area.x1, cut, area.x2, area.y2), rng, leaves);
+        }
+    }
+}
+
+impl MapBuilder for BspBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let mut map = Map::blank(data.map.depth, MAP_WIDTH, MAP_HEIGHT);
+        let mut leaves = vec![];
+        self.split(
+            Rect::with_exact(1, 1, MAP_WIDTH - 1, MAP_HEIGHT - 1),
+            rng,
+            &mut leaves,
+        );
+
+        // leave a tile of rock around each room so neighbouring ones don't run together
+        let rooms: Vec<Rect> = leaves
+            .iter()
+            .map(|leaf| {
+                let width = rng.range(4, leaf.width() - 1);
+                let height = rng.range(4, leaf.height() - 1);
+                let x = leaf.x1 + 1 + rng.range(0, leaf.width() - 1 - width);
+                let y = leaf.y1 + 1 + rng.range(0, leaf.height() - 1 - height);
+                Rect::with_size(x, y, width, height)
+            })
+            .collect();
+        for room in rooms.iter() {
+            dig_room(&mut map, room);
+        }
+        for pair in rooms.windows(2) {
+            dig_hall(&mut map, pair[0].center(), pair[1].center());
+        }
+
+        map.rooms = rooms;
+        data.map = map;
+        data.start = Some(data.map.rooms[0].center());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rooms_stay_in_their_leaves() {
+        let mut rng = RandomNumberGenerator::seeded(11);
+        let builder = BspBuilder::default();
+        let mut leaves = vec![];
+        builder.split(Rect::with_exact(1, 1, 69, 59), &mut rng, &mut leaves);
+        assert!(leaves.len() >= 4);
+        for (i, a) in leaves.iter().enumerate() {
+            assert!(a.width() >= builder.min_leaf && a.height() >= builder.min_leaf);
+            // neighbours share an edge, `Rect::intersect` counts that as touching
+            for b in leaves[i + 1..].iter() {
+                assert!(a.x2 <= b.x1 || b.x2 <= a.x1 || a.y2 <= b.y1 || b.y2 <= a.y1);
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Caves grown with a cellular automaton: start from noise, then repeatedly turn each tile into whatever most of
+//! its neighbours are. Whatever can't be reached from the middle gets filled back in.
+
+use bracket_lib::prelude::*;
+
+use super::{
+    nearest_floor, remove_unreachable, spawn_regions, BuildData, MapBuilder, MAP_HEIGHT, MAP_WIDTH,
+};
+use crate::map::{Map, Tile};
+
+#[derive(Clone, Debug)]
+pub struct CaveBuilder {
+    /// chance out of 100 that a tile starts as wall
+    pub fill_percent: i32,
+    pub iterations: usize,
+    /// a cave with less floor than this after the unreachable bits are gone gets thrown out
+    pub min_open: usize,
+}
+
+impl Default for CaveBuilder {
+    fn default() -> Self {
+        CaveBuilder {
+            fill_percent: 45,
+            iterations: 5,
+            min_open: (MAP_WIDTH * MAP_HEIGHT / 3) as usize,
+        }
+    }
+}
+
+/// size of the chunks open levels get cut into for spawn regions, and how much of one has to be open to count
+pub const REGION_SIZE: i32 = 10;
+pub const REGION_MIN_OPEN: usize = 12;
+
+impl MapBuilder for CaveBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        loop {
+            let mut map = Map::blank(data.map.depth, MAP_WIDTH, MAP_HEIGHT);
+            for y in 1..map.height - 1 {
+                for x in 1..map.width - 1 {
+                    if rng.range(0, 100) >= self.fill_percent {
+                        let idx = map.point2d_to_index(Point::new(x, y));
+                        map.tiles[idx] = Tile::Floor;
+                    }
+                }
+            }
+            for _ in 0..self.iterations {
+                smooth(&mut map);
+            }
+
+            let Some(start) = nearest_floor(&map, Point::new(map.width / 2, map.height / 2)) else {
+                continue;
+            };
+            if remove_unreachable(&mut map, start) < self.min_open {
+                continue;
+            }
+
+            // the first region is always the start, everything else is where the cave happens to be
+            let mut rooms = vec![Rect::with_size(start.x - 2, start.y - 2, 5, 5)];
+            rooms.extend(spawn_regions(&map, REGION_SIZE, REGION_MIN_OPEN));
+            map.rooms = rooms;
+            data.map = map;
+            data.start = Some(start);
+            return;
+        }
+    }
+}
+
+fn smooth(map: &mut Map) {
+    let mut tiles = map.tiles.clone();
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let mut walls = 0;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if (dx, dy) == (0, 0) {
+                        continue;
+                    }
+                    if map.tiles[map.point2d_to_index(Point::new(x + dx, y + dy))] == Tile::Wall {
+                        walls += 1;
+                    }
+                }
+            }
+            let idx = map.point2d_to_index(Point::new(x, y));
+            tiles[idx] = if walls >= 5 { Tile::Wall } else { Tile::Floor };
+        }
+    }
+    map.tiles = tiles;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_builders::{walk_distances, BuilderChain};
+
+    fn cave(seed: u64) -> Map {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        BuilderChain::new(1, None)
+            .with(Box::new(CaveBuilder::default()))
+            .build(&mut rng)
+    }
+
+    #[test]
+    fn everything_is_reachable() {
+        for seed in 0..5 {
+            let map = cave(seed);
+            assert_ne!(map.tiles[map.point2d_to_index(map.start)], Tile::Wall);
+            let distances = walk_distances(&map, map.start);
+            for (idx, tile) in map.tiles.iter().enumerate() {
+                assert_eq!(tile.passable(), distances[idx].is_some());
+            }
+            assert!(map.tiles.contains(&Tile::Stairs));
+        }
+    }
+
+    #[test]
+    fn every_region_has_floor() {
+        let map = cave(3);
+        assert!(map.rooms.len() > 1);
+        for room in map.rooms.iter() {
+            let mut open = false;
+            room.for_each(|pt| open |= map.tiles[map.point2d_to_index(pt)] != Tile::Wall);
+            assert!(open);
+        }
+    }
+}
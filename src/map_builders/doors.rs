@@ -0,0 +1,76 @@
+//! Doors in the gaps where halls meet rooms: a floor tile just outside a room with wall on either side of it.
+
+use bracket_lib::prelude::*;
+
+use super::{BuildData, MapBuilder};
+use crate::map::{Map, Tile};
+
+#[derive(Clone, Debug)]
+pub struct DoorBuilder {
+    /// chance out of 100 of each doorway getting a door
+    pub chance: i32,
+}
+
+impl MapBuilder for DoorBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let map = &mut data.map;
+        let mut doorways = vec![];
+        for room in map.rooms.iter() {
+            for x in room.x1..room.x2 {
+                doorways.push((Point::new(x, room.y1 - 1), Point::new(1, 0)));
+                doorways.push((Point::new(x, room.y2), Point::new(1, 0)));
+            }
+            for y in room.y1..room.y2 {
+                doorways.push((Point::new(room.x1 - 1, y), Point::new(0, 1)));
+                doorways.push((Point::new(room.x2, y), Point::new(0, 1)));
+            }
+        }
+        for (pt, along) in doorways {
+            if is_doorway(map, pt, along) && rng.range(0, 100) < self.chance {
+                let idx = map.point2d_to_index(pt);
+                map.tiles[idx] = Tile::Door { open: false };
+            }
+        }
+    }
+}
+
+/// Floor, with wall either side of it going `along` the room's edge
+fn is_doorway(map: &Map, pt: Point, along: Point) -> bool {
+    let is = |pt: Point, tile: Tile| map.in_bounds(pt) && map.tiles[map.point2d_to_index(pt)] == tile;
+    is(pt, Tile::Floor) && is(pt - along, Tile::Wall) && is(pt + along, Tile::Wall)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doors_go_in_gaps() {
+        let mut map = Map::blank(0, 20, 10);
+        let room = Rect::with_size(2, 2, 5, 5);
+        room.for_each(|pt| {
+            let idx = map.point2d_to_index(pt);
+            map.tiles[idx] = Tile::Floor;
+        });
+        // a hall leaving the right side of the room
+        for x in 7..15 {
+            let idx = map.point2d_to_index(Point::new(x, 4));
+            map.tiles[idx] = Tile::Floor;
+        }
+        map.rooms = vec![room];
+        let mut data = BuildData {
+            map,
+            start: None,
+            stairs: None,
+            element: None,
+            spawns: vec![],
+        };
+        let mut rng = RandomNumberGenerator::seeded(1);
+        DoorBuilder { chance: 100 }.build(&mut data, &mut rng);
+        let doors: Vec<Point> = (0..data.map.tiles.len())
+            .filter(|i| data.map.tiles[*i].is_closed_door())
+            .map(|i| data.map.index_to_point2d(i))
+            .collect();
+        assert_eq!(doors, vec![Point::new(7, 4)]);
+    }
+}
@@ -0,0 +1,61 @@
+//! Drunkard's walk: diggers stumble about at random, each starting somewhere already dug, until enough of the
+//! level is open. Everything they dig is connected by construction.
+
+use bracket_lib::prelude::*;
+
+use super::{
+    caves::{REGION_MIN_OPEN, REGION_SIZE},
+    spawn_regions, BuildData, MapBuilder, MAP_HEIGHT, MAP_WIDTH,
+};
+use crate::map::{Map, Tile};
+
+#[derive(Clone, Debug)]
+pub struct DrunkardBuilder {
+    /// how much of the level gets dug out, out of 100
+    pub floor_percent: i32,
+    /// steps each digger takes before the next one starts
+    pub lifetime: i32,
+}
+
+impl Default for DrunkardBuilder {
+    fn default() -> Self {
+        DrunkardBuilder {
+            floor_percent: 40,
+            lifetime: 400,
+        }
+    }
+}
+
+impl MapBuilder for DrunkardBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let mut map = Map::blank(data.map.depth, MAP_WIDTH, MAP_HEIGHT);
+        let start = Point::new(map.width / 2, map.height / 2);
+        let start_idx = map.point2d_to_index(start);
+        map.tiles[start_idx] = Tile::Floor;
+        let target = (map.tiles.len() as i32 * self.floor_percent / 100) as usize;
+        let mut dug = 1;
+        while dug < target {
+            let floor: Vec<usize> = (0..map.tiles.len())
+                .filter(|i| map.tiles[*i] == Tile::Floor)
+                .collect();
+            let mut pos = map.index_to_point2d(floor[rng.range(0, floor.len())]);
+            for _ in 0..self.lifetime {
+                let idx = map.point2d_to_index(pos);
+                if map.tiles[idx] == Tile::Wall {
+                    map.tiles[idx] = Tile::Floor;
+                    dug += 1;
+                }
+                let (dx, dy) = [(-1, 0), (1, 0), (0, -1), (0, 1)][rng.range(0, 4)];
+                // never dig into the edge of the map
+                pos.x = (pos.x + dx).clamp(1, map.width - 2);
+                pos.y = (pos.y + dy).clamp(1, map.height - 2);
+            }
+        }
+
+        let mut rooms = vec![Rect::with_size(start.x - 2, start.y - 2, 5, 5)];
+        rooms.extend(spawn_regions(&map, REGION_SIZE, REGION_MIN_OPEN));
+        map.rooms = rooms;
+        data.map = map;
+        data.start = Some(start);
+    }
+}
@@ -0,0 +1,192 @@
+//! Hand-made pieces of level, written as rows of glyphs in `raws/prefabs.json` or drawn in REXPaint. A prefab can
+//! be a whole level, or a vault that gets stamped into the depths it's allowed on, see `vault.rs`.
+
+use bracket_lib::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    caves::{REGION_MIN_OPEN, REGION_SIZE},
+    spawn_regions, BuildData, MapBuilder,
+};
+use crate::{
+    components::Elemental,
+    map::{Map, Spawn, Tile},
+    raws::RAWS,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PrefabDef {
+    /// rows of glyphs, see `Cell::from_glyph`
+    #[serde(default)]
+    pub template: Vec<String>,
+    /// a REXPaint image to read the template from instead, which has to be linked in `raws.rs`
+    #[serde(default)]
+    pub xp: Option<String>,
+    /// the shallowest and deepest depths it turns up on as a vault, never if left out
+    #[serde(default)]
+    pub depths: Option<(i32, i32)>,
+    /// chance out of 100 of it turning up on each of those depths
+    #[serde(default)]
+    pub chance: i32,
+    /// only on floors of this element
+    #[serde(default)]
+    pub element: Option<Elemental>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cell {
+    /// whatever was there already
+    Keep,
+    Tile(Tile),
+    /// floor, where the player arrives
+    Start,
+    /// floor, with something put on it once the level is populated
+    Spawn(Spawn),
+}
+
+impl Cell {
+    pub fn from_glyph(c: char) -> Option<Cell> {
+        Some(match c {
+            ' ' => Cell::Keep,
+            '#' => Cell::Tile(Tile::Wall),
+            '.' => Cell::Tile(Tile::Floor),
+            '>' => Cell::Tile(Tile::Stairs),
+            '+' => Cell::Tile(Tile::Door { open: false }),
+            '\'' => Cell::Tile(Tile::Door { open: true }),
+            '=' => Cell::Tile(Tile::Lava),
+            '~' => Cell::Tile(Tile::DeepWater),
+            ',' => Cell::Tile(Tile::ShallowWater),
+            ':' => Cell::Tile(Tile::Rubble),
+            '_' => Cell::Tile(Tile::Chasm),
+            '^' => Cell::Tile(Tile::Trap),
+            '@' => Cell::Start,
+            'M' => Cell::Spawn(Spawn::Monster),
+            'I' => Cell::Spawn(Spawn::Item),
+            '1'..='9' => Cell::Spawn(Spawn::Boss(c as usize - '1' as usize)),
+            _ => return None,
+        })
+    }
+
+    pub fn tile(self) -> Option<Tile> {
+        match self {
+            Cell::Keep => None,
+            Cell::Tile(tile) => Some(tile),
+            Cell::Start | Cell::Spawn(_) => Some(Tile::Floor),
+        }
+    }
+}
+
+impl PrefabDef {
+    /// The template as cells, or the first glyph that isn't one
+    pub fn cells(&self) -> Result<Vec<Vec<Cell>>, char> {
+        self.template
+            .iter()
+            .map(|row| row.chars().map(|c| Cell::from_glyph(c).ok_or(c)).collect())
+            .collect()
+    }
+
+    pub fn allowed_on(&self, depth: i32, element: Option<&Elemental>) -> bool {
+        let Some((shallowest, deepest)) = self.depths else { return false };
+        (shallowest..=deepest).contains(&depth)
+            && self.element.as_ref().map_or(true, |e| Some(e) == element)
+    }
+}
+
+pub fn size(cells: &[Vec<Cell>]) -> Point {
+    let width = cells.iter().map(Vec::len).max().unwrap_or(0);
+    Point::new(width as i32, cells.len() as i32)
+}
+
+/// Reads a template out of the first layer of a REXPaint image, a glyph per cell
+pub fn xp_template(xp: &XpFile) -> Vec<String> {
+    let layer = &xp.layers[0];
+    (0..layer.height)
+        .map(|y| {
+            (0..layer.width)
+                .map(|x| match layer.get(x, y).map_or(0, |cell| cell.ch) {
+                    0 => ' ',
+                    ch => to_char(ch as u8),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Copies `cells` onto the map with their top left at `origin`, noting any start, stairs and spawns
+pub fn stamp(data: &mut BuildData, cells: &[Vec<Cell>], origin: Point) {
+    for (y, row) in cells.iter().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let pt = origin + Point::new(x as i32, y as i32);
+            let Some(tile) = cell.tile() else { continue };
+            if !data.map.in_bounds(pt) {
+                continue;
+            }
+            let idx = data.map.point2d_to_index(pt);
+            data.map.tiles[idx] = tile;
+            match cell {
+                Cell::Start => data.start = Some(pt),
+                Cell::Spawn(spawn) => data.spawns.push((pt, *spawn)),
+                Cell::Tile(Tile::Stairs) => data.stairs = Some(pt),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A whole level laid out from a prefab
+#[derive(Clone, Debug)]
+pub struct PrefabBuilder {
+    pub name: String,
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build(&mut self, data: &mut BuildData, _rng: &mut RandomNumberGenerator) {
+        let def = RAWS
+            .lock()
+            .unwrap()
+            .prefabs
+            .get(&self.name)
+            .cloned()
+            .unwrap_or_else(|| panic!("No prefab called {}", self.name));
+        let cells = def
+            .cells()
+            .unwrap_or_else(|c| panic!("Prefab {} has an unknown glyph {:?}", self.name, c));
+        let dims = size(&cells);
+        data.map = Map::blank(data.map.depth, dims.x, dims.y);
+        stamp(data, &cells, Point::zero());
+        data.map.rooms = spawn_regions(&data.map, REGION_SIZE, REGION_MIN_OPEN);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raws::load_prefabs;
+
+    #[test]
+    fn every_prefab_parses() {
+        for (name, def) in load_prefabs() {
+            let cells = def.cells().unwrap_or_else(|c| panic!("{name}: {c:?}"));
+            assert!(!cells.is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn xp_prefabs_read_like_text() {
+        let prefabs = load_prefabs();
+        let shrine = &prefabs["fire_shrine"];
+        assert_eq!(shrine.template[3], "+=.^I^.=+");
+    }
+
+    #[test]
+    fn final_arena_has_everyone() {
+        let prefabs = load_prefabs();
+        let cells = prefabs["final_arena"].cells().unwrap();
+        let all: Vec<Cell> = cells.into_iter().flatten().collect();
+        assert_eq!(all.iter().filter(|c| **c == Cell::Start).count(), 1);
+        for boss in 0..3 {
+            assert!(all.contains(&Cell::Spawn(Spawn::Boss(boss))));
+        }
+        assert!(all.contains(&Cell::Tile(Tile::Stairs)));
+    }
+}
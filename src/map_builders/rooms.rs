@@ -0,0 +1,59 @@
+use bracket_lib::prelude::*;
+
+use super::{dig_hall, dig_room, BuildData, MapBuilder, MAP_HEIGHT, MAP_WIDTH};
+use crate::map::{random_room_point, Map};
+
+/// Rectangular rooms, each joined to the next by an L-shaped hall
+#[derive(Clone, Debug)]
+pub struct RoomsBuilder {
+    pub room_count: usize,
+}
+
+impl Default for RoomsBuilder {
+    fn default() -> Self {
+        RoomsBuilder { room_count: 15 }
+    }
+}
+
+impl MapBuilder for RoomsBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let mut map = Map::blank(data.map.depth, MAP_WIDTH, MAP_HEIGHT);
+
+        let mut rooms = Vec::<Rect>::new();
+
+        for _i in 0..self.room_count {
+            let new_room = loop {
+                let room_center = (
+                    rng.range(10, MAP_WIDTH - 10),
+                    rng.range(10, MAP_HEIGHT - 10),
+                );
+                let width = rng.range(5, 9);
+                let height = rng.range(5, 9);
+                let room = Rect::with_size(
+                    room_center.0 - width / 2,
+                    room_center.1 - height / 2,
+                    width,
+                    height,
+                );
+
+                if !rooms.iter().any(|r| room.intersect(r)) {
+                    break room;
+                }
+            };
+            rooms.push(new_room);
+        }
+
+        for room in rooms.iter() {
+            dig_room(&mut map, room);
+        }
+        // connect each room to the next
+        for pair in rooms.windows(2) {
+            dig_hall(&mut map, pair[0].center(), pair[1].center());
+        }
+
+        map.rooms = rooms;
+        data.stairs = random_room_point(&map, rng);
+        data.start = Some(map.rooms[0].center());
+        data.map = map;
+    }
+}
@@ -0,0 +1,202 @@
+//! Ground that matches the floor's element: lava and rubble on fire floors, deep water fringed with shallows on
+//! water floors, and wind tunnels and chasms on air floors. It's kept out of the starting region, and chasms are
+//! only left where they don't cut anything off.
+
+use bracket_lib::prelude::*;
+
+use super::{walk_distances, BuildData, MapBuilder};
+use crate::{
+    components::Elemental,
+    map::{Direction, Map, Tile},
+};
+
+#[derive(Clone, Debug)]
+pub struct TerrainBuilder {
+    /// how many pools or tunnels to try for
+    pub patches: usize,
+}
+
+impl Default for TerrainBuilder {
+    fn default() -> Self {
+        TerrainBuilder { patches: 6 }
+    }
+}
+
+/// steps the digger takes making each pool
+const POOL_SIZE: usize = 16;
+/// every this many patches is something other than the floor's main feature
+const SECONDARY_EVERY: usize = 3;
+/// wind tunnels shorter than this aren't worth having
+const MIN_TUNNEL: usize = 4;
+const MAX_TUNNEL: usize = 10;
+
+impl MapBuilder for TerrainBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let Some(element) = data.element.clone() else { return };
+        let start = data
+            .start
+            .or_else(|| data.map.rooms.first().map(|r| r.center()))
+            .unwrap_or_else(Point::zero);
+        let safe = match data.map.rooms.first() {
+            Some(room) if room.point_in_rect(start) => *room,
+            _ => Rect::with_size(start.x - 2, start.y - 2, 5, 5),
+        };
+        // and a tile around it, so you don't step straight out into trouble
+        let safe = Rect::with_exact(safe.x1 - 1, safe.y1 - 1, safe.x2 + 1, safe.y2 + 1);
+        let map = &mut data.map;
+        for i in 0..self.patches {
+            let Some(origin) = random_open(map, &safe, rng) else { break };
+            let secondary = i % SECONDARY_EVERY == SECONDARY_EVERY - 1;
+            match (element.clone(), secondary) {
+                (Elemental::Fire, false) => pool(map, &safe, origin, Tile::Lava, rng),
+                (Elemental::Fire, true) => pool(map, &safe, origin, Tile::Rubble, rng),
+                (Elemental::Water, _) => pool(map, &safe, origin, Tile::DeepWater, rng),
+                (Elemental::Air, false) => tunnel(map, &safe, origin, rng),
+                (Elemental::Air, true) => chasm(map, &safe, origin, start, rng),
+            }
+        }
+        if element == Elemental::Water {
+            shallows(map, &safe);
+        }
+    }
+}
+
+fn placeable(map: &Map, safe: &Rect, pt: Point) -> bool {
+    map.in_bounds(pt) && !safe.point_in_rect(pt) && map.tiles[map.point2d_to_index(pt)] == Tile::Floor
+}
+
+fn random_open(map: &Map, safe: &Rect, rng: &mut RandomNumberGenerator) -> Option<Point> {
+    (0..100)
+        .map(|_| Point::new(rng.range(0, map.width), rng.range(0, map.height)))
+        .find(|pt| placeable(map, safe, *pt))
+}
+
+fn pool(map: &mut Map, safe: &Rect, origin: Point, tile: Tile, rng: &mut RandomNumberGenerator) {
+    let mut pos = origin;
+    for _ in 0..POOL_SIZE {
+        if placeable(map, safe, pos) {
+            let idx = map.point2d_to_index(pos);
+            map.tiles[idx] = tile;
+        }
+        let next = pos + Direction::ALL[rng.range(0, 4)].delta();
+        if map.in_bounds(next) && map.tiles[map.point2d_to_index(next)] != Tile::Wall {
+            pos = next;
+        }
+    }
+}
+
+/// Chasms can't be crossed, so one is only kept if everything that could be reached before still can be
+fn chasm(map: &mut Map, safe: &Rect, origin: Point, start: Point, rng: &mut RandomNumberGenerator) {
+    let reachable = |map: &Map| walk_distances(map, start).iter().filter(|d| d.is_some()).count();
+    let before = reachable(map);
+    let old = map.tiles.clone();
+    pool(map, safe, origin, Tile::Chasm, rng);
+    let dug = map.tiles.iter().zip(old.iter()).filter(|(a, b)| a != b).count();
+    if reachable(map) + dug < before {
+        map.tiles = old;
+    }
+}
+
+/// Edges deep water with shallow water
+fn shallows(map: &mut Map, safe: &Rect) {
+    let edges: Vec<Point> = (0..map.tiles.len())
+        .map(|idx| map.index_to_point2d(idx))
+        .filter(|pt| placeable(map, safe, *pt))
+        .filter(|pt| {
+            Direction::ALL.iter().any(|dir| {
+                let next = *pt + dir.delta();
+                map.in_bounds(next) && map.tiles[map.point2d_to_index(next)] == Tile::DeepWater
+            })
+        })
+        .collect();
+    for pt in edges {
+        let idx = map.point2d_to_index(pt);
+        map.tiles[idx] = Tile::ShallowWater;
+    }
+}
+
+/// A straight run of wind along whatever open ground there is in a random direction
+fn tunnel(map: &mut Map, safe: &Rect, origin: Point, rng: &mut RandomNumberGenerator) {
+    let dir = Direction::ALL[rng.range(0, 4)];
+    let run: Vec<Point> = (0..MAX_TUNNEL as i32)
+        .map(|i| origin + dir.delta() * i)
+        .take_while(|pt| placeable(map, safe, *pt))
+        .collect();
+    if run.len() < MIN_TUNNEL {
+        return;
+    }
+    for pt in run {
+        let idx = map.point2d_to_index(pt);
+        map.tiles[idx] = Tile::Wind(dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_map() -> BuildData {
+        let mut map = Map::blank(1, 30, 30);
+        for y in 1..29 {
+            for x in 1..29 {
+                let idx = map.point2d_to_index(Point::new(x, y));
+                map.tiles[idx] = Tile::Floor;
+            }
+        }
+        map.rooms = vec![Rect::with_size(2, 2, 5, 5)];
+        BuildData {
+            map,
+            start: Some(Point::new(4, 4)),
+            stairs: None,
+            element: Some(Elemental::Fire),
+            spawns: vec![],
+        }
+    }
+
+    #[test]
+    fn start_stays_clear() {
+        let mut data = open_map();
+        let mut rng = RandomNumberGenerator::seeded(9);
+        TerrainBuilder { patches: 40 }.build(&mut data, &mut rng);
+        assert!(data.map.tiles.contains(&Tile::Lava));
+        for y in 1..8 {
+            for x in 1..8 {
+                let idx = data.map.point2d_to_index(Point::new(x, y));
+                assert_eq!(data.map.tiles[idx], Tile::Floor);
+            }
+        }
+    }
+
+    #[test]
+    fn unaligned_floors_are_left_alone() {
+        let mut data = open_map();
+        data.element = None;
+        let before = data.map.tiles.clone();
+        let mut rng = RandomNumberGenerator::seeded(9);
+        TerrainBuilder::default().build(&mut data, &mut rng);
+        assert_eq!(data.map.tiles, before);
+    }
+
+    #[test]
+    fn chasms_cut_nothing_off() {
+        let mut data = open_map();
+        // a one tile wide corridor out of the open area, which any chasm in it would cut
+        for y in 1..29 {
+            let idx = data.map.point2d_to_index(Point::new(20, y));
+            data.map.tiles[idx] = if y == 15 { Tile::Floor } else { Tile::Wall };
+        }
+        data.element = Some(Elemental::Air);
+        let before = walk_distances(&data.map, Point::new(4, 4))
+            .iter()
+            .filter(|d| d.is_some())
+            .count();
+        let mut rng = RandomNumberGenerator::seeded(2);
+        TerrainBuilder { patches: 30 }.build(&mut data, &mut rng);
+        let chasms = data.map.tiles.iter().filter(|t| **t == Tile::Chasm).count();
+        let after = walk_distances(&data.map, Point::new(4, 4))
+            .iter()
+            .filter(|d| d.is_some())
+            .count();
+        assert_eq!(after + chasms, before);
+    }
+}
@@ -0,0 +1,169 @@
+//! Vaults are prefabs dug into solid rock next to the rest of the level. Whatever on a vault's edge can be walked
+//! through is an entrance, and entrances that would open onto rock are walled up again. Each vault becomes a spawn
+//! region of its own.
+
+use bracket_lib::prelude::*;
+
+use super::{
+    prefab::{self, Cell, PrefabDef},
+    BuildData, MapBuilder,
+};
+use crate::{map::Tile, raws::RAWS};
+
+#[derive(Clone, Debug)]
+pub struct VaultBuilder;
+
+/// how many spots get tried before giving up on a vault
+const ATTEMPTS: usize = 50;
+
+impl MapBuilder for VaultBuilder {
+    fn build(&mut self, data: &mut BuildData, rng: &mut RandomNumberGenerator) {
+        let mut allowed: Vec<(String, PrefabDef)> = RAWS
+            .lock()
+            .unwrap()
+            .prefabs
+            .iter()
+            .filter(|(_, def)| def.allowed_on(data.map.depth, data.element.as_ref()))
+            .map(|(name, def)| (name.clone(), def.clone()))
+            .collect();
+        // the same seed has to dig the same vaults
+        allowed.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, def) in allowed {
+            if rng.range(0, 100) >= def.chance {
+                continue;
+            }
+            let cells = def
+                .cells()
+                .unwrap_or_else(|c| panic!("Prefab {name} has an unknown glyph {c:?}"));
+            place(data, &cells, rng);
+        }
+    }
+}
+
+/// Digs `cells` in somewhere they have rock all round and at least one entrance onto the level, returning whether
+/// anywhere was found
+pub fn place(data: &mut BuildData, cells: &[Vec<Cell>], rng: &mut RandomNumberGenerator) -> bool {
+    let size = prefab::size(cells);
+    if size.x < 3 || size.y < 3 || size.x + 2 > data.map.width || size.y + 2 > data.map.height {
+        return false;
+    }
+    for _ in 0..ATTEMPTS {
+        let origin = Point::new(
+            rng.range(1, data.map.width - size.x),
+            rng.range(1, data.map.height - size.y),
+        );
+        let map = &data.map;
+        let at = |x: usize, y: usize| origin + Point::new(x as i32, y as i32);
+
+        // only solid rock, so nothing that was connected gets cut off
+        let solid = cells.iter().enumerate().all(|(y, row)| {
+            row.iter().enumerate().all(|(x, cell)| {
+                *cell == Cell::Keep || map.tiles[map.point2d_to_index(at(x, y))] == Tile::Wall
+            })
+        });
+        if !solid {
+            continue;
+        }
+        let mut entrances: Vec<(Point, bool)> = vec![];
+        for (y, row) in cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let Some(out) = outward(x as i32, y as i32, size) else { continue };
+                if !cell.tile().map_or(false, |t| t.passable()) {
+                    continue;
+                }
+                let outside = at(x, y) + out;
+                let open =
+                    map.in_bounds(outside) && map.tiles[map.point2d_to_index(outside)].walkable();
+                entrances.push((at(x, y), open));
+            }
+        }
+        if !entrances.iter().any(|(_, open)| *open) {
+            continue;
+        }
+
+        prefab::stamp(data, cells, origin);
+        for (pt, open) in entrances {
+            if !open {
+                let idx = data.map.point2d_to_index(pt);
+                data.map.tiles[idx] = Tile::Wall;
+            }
+        }
+        data.map.rooms.push(Rect::with_size(
+            origin.x + 1,
+            origin.y + 1,
+            size.x - 2,
+            size.y - 2,
+        ));
+        return true;
+    }
+    false
+}
+
+/// Which way is out of the prefab from a cell on its edge. Corners don't count, they can't be walked out of
+/// without cutting one.
+fn outward(x: i32, y: i32, size: Point) -> Option<Point> {
+    let horizontal = if x == 0 {
+        Some(Point::new(-1, 0))
+    } else if x == size.x - 1 {
+        Some(Point::new(1, 0))
+    } else {
+        None
+    };
+    let vertical = if y == 0 {
+        Some(Point::new(0, -1))
+    } else if y == size.y - 1 {
+        Some(Point::new(0, 1))
+    } else {
+        None
+    };
+    match (horizontal, vertical) {
+        (Some(out), None) | (None, Some(out)) => Some(out),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{Map, Spawn};
+
+    fn cells(rows: &[&str]) -> Vec<Vec<Cell>> {
+        rows.iter()
+            .map(|row| row.chars().map(|c| Cell::from_glyph(c).unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn vaults_open_onto_the_level() {
+        let mut map = Map::blank(0, 30, 20);
+        for x in 1..29 {
+            let idx = map.point2d_to_index(Point::new(x, 10));
+            map.tiles[idx] = Tile::Floor;
+        }
+        let mut data = BuildData {
+            map,
+            start: Some(Point::new(1, 10)),
+            stairs: None,
+            element: None,
+            spawns: vec![],
+        };
+        let chamber = cells(&[
+            "###+###", "#.....#", "#..I..#", "+..M..+", "#.....#", "###+###",
+        ]);
+        let mut rng = RandomNumberGenerator::seeded(5);
+        assert!(place(&mut data, &chamber, &mut rng));
+        let vault = *data.map.rooms.last().unwrap();
+        let mut open = 0;
+        vault.for_each(|pt| {
+            if data.map.tiles[data.map.point2d_to_index(pt)] == Tile::Floor {
+                open += 1;
+            }
+        });
+        assert_eq!(open, vault.width() * vault.height());
+        assert_eq!(data.spawns.len(), 2);
+        assert!(data.spawns.iter().any(|(_, s)| *s == Spawn::Item));
+        // the vault sits wholly above or below the corridor, so exactly one door leads anywhere
+        let doors = data.map.tiles.iter().filter(|t| t.is_closed_door()).count();
+        assert_eq!(doors, 1);
+    }
+}
@@ -1,6 +1,7 @@
 use bracket_lib::terminal::{BTerm, Point, VirtualKeyCode};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Command {
     Move { target: Point },
     Grab,
@@ -10,11 +11,17 @@ pub enum Command {
     Back,
     OpenExamine,
     DescendStairs,
+    AscendStairs,
     Select,
     CreateItem,
-    UseActive(i32),
+    /// a digit key: uses an active artifact in game, types into text fields elsewhere
+    Number(i32),
+    Erase,
     EquipExamine,
     GetHelp,
+    SaveGame,
+    OpenDoor,
+    CloseDoor,
 }
 
 pub fn get_command(ctx: &mut BTerm) -> Option<Command> {
@@ -22,7 +29,10 @@ pub fn get_command(ctx: &mut BTerm) -> Option<Command> {
         if ctx.shift {
             match key {
                 VirtualKeyCode::Period => Some(Command::DescendStairs),
+                VirtualKeyCode::Comma => Some(Command::AscendStairs),
                 VirtualKeyCode::Slash => Some(Command::GetHelp),
+                VirtualKeyCode::S => Some(Command::SaveGame),
+                VirtualKeyCode::C => Some(Command::CloseDoor),
                 _ => None,
             }
         } else {
@@ -63,6 +73,7 @@ pub fn get_command(ctx: &mut BTerm) -> Option<Command> {
                     target: Point::new(1, 1),
                 }),
                 VirtualKeyCode::G => Some(Command::Grab),
+                VirtualKeyCode::O => Some(Command::OpenDoor),
                 VirtualKeyCode::I => Some(Command::OpenInventory),
                 VirtualKeyCode::M => Some(Command::OpenMessageLog),
                 VirtualKeyCode::X | VirtualKeyCode::Slash => Some(Command::OpenExamine),
@@ -70,15 +81,17 @@ pub fn get_command(ctx: &mut BTerm) -> Option<Command> {
                 VirtualKeyCode::Period => Some(Command::Wait),
                 VirtualKeyCode::Escape | VirtualKeyCode::Q => Some(Command::Back),
                 VirtualKeyCode::Return => Some(Command::Select),
-                VirtualKeyCode::Key1 => Some(Command::UseActive(1)),
-                VirtualKeyCode::Key2 => Some(Command::UseActive(2)),
-                VirtualKeyCode::Key3 => Some(Command::UseActive(3)),
-                VirtualKeyCode::Key4 => Some(Command::UseActive(4)),
-                VirtualKeyCode::Key5 => Some(Command::UseActive(5)),
-                VirtualKeyCode::Key6 => Some(Command::UseActive(6)),
-                VirtualKeyCode::Key7 => Some(Command::UseActive(7)),
-                VirtualKeyCode::Key8 => Some(Command::UseActive(8)),
-                VirtualKeyCode::Key9 => Some(Command::UseActive(9)),
+                VirtualKeyCode::Back => Some(Command::Erase),
+                VirtualKeyCode::Key0 => Some(Command::Number(0)),
+                VirtualKeyCode::Key1 => Some(Command::Number(1)),
+                VirtualKeyCode::Key2 => Some(Command::Number(2)),
+                VirtualKeyCode::Key3 => Some(Command::Number(3)),
+                VirtualKeyCode::Key4 => Some(Command::Number(4)),
+                VirtualKeyCode::Key5 => Some(Command::Number(5)),
+                VirtualKeyCode::Key6 => Some(Command::Number(6)),
+                VirtualKeyCode::Key7 => Some(Command::Number(7)),
+                VirtualKeyCode::Key8 => Some(Command::Number(8)),
+                VirtualKeyCode::Key9 => Some(Command::Number(9)),
                 _ => None,
             }
         }
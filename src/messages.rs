@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct MessageLog {
     pub log: Vec<String>,
     pub current_messages: Vec<String>,
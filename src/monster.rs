@@ -1,106 +1,245 @@
-use crate::{components::*, equipment::execute_defence_effects, raws::RAWS, State};
+use crate::{
+    boss::boss_act,
+    combat::{post, Event},
+    components::*,
+    components_serde::Component,
+    map::Tile,
+    pathing::{monster_at, SearchArea},
+    perception::{forget, perceive, SEARCH_RADIUS},
+    raws::RAWS,
+    scheduler::ACTION_COST,
+    spawn_table::{lookup, pick, SpawnEntry},
+    State,
+};
 use bracket_lib::prelude::*;
 use hecs::Entity;
 use rand::{distributions::WeightedIndex, prelude::Distribution};
 
-pub fn monster_act(state: &mut State, entity: Entity) {
+/// Returns the energy the monster spent
+pub fn monster_act(state: &mut State, entity: Entity) -> i32 {
     let player_pos = state
         .ecs
         .query_one_mut::<&Position>(state.player_entity)
         .unwrap()
         .0
         .clone();
-    let (mon, viewer, skilled) = state
-        .ecs
-        .query_one_mut::<(&mut Monster, Option<&Viewer>, Option<&Skilled>)>(entity)
-        .unwrap();
-    let mut target = None;
-    if let Some(viewer) = viewer.as_ref() {
-        if viewer.visible_tiles.contains(&player_pos) {
-            target = Some(player_pos);
-            mon.tracking = target;
-
-            if let Some(skilled) = skilled {
-                let len = skilled.skills.len();
-                let mut weights: Vec<f32> =
-                    skilled.skills.iter().map(|x| x.0 / len as f32).collect();
-                weights.push(1.0); // for normal action
-                let dist = WeightedIndex::new(weights).unwrap();
-                let roll = dist.sample(state.rng.get_rng());
-                let mut success = false;
-                if roll != len {
-                    // if not normal attack
-                    let skill = skilled.skills[roll];
-                    success = skill.1.apply(entity, state.player_entity, state).is_some();
-                }
-                if success {
-                    return;
+    let sees_player = perceive(state, entity, player_pos);
+    let mon = state.ecs.query_one_mut::<&Monster>(entity).unwrap();
+    if mon.awareness == Awareness::Asleep {
+        return ACTION_COST;
+    }
+    if let Some(cost) = boss_act(state, entity, sees_player) {
+        return cost;
+    }
+    let skilled = state.ecs.query_one_mut::<Option<&Skilled>>(entity).unwrap();
+    if sees_player {
+        if let Some(skilled) = skilled {
+            let len = skilled.skills.len();
+            // skills still cooling down can't be picked
+            let ready: Vec<usize> = (0..len).filter(|idx| skilled.ready(*idx)).collect();
+            let mut weights: Vec<f32> = ready
+                .iter()
+                .map(|idx| skilled.skills[*idx].0 / len as f32)
+                .collect();
+            weights.push(1.0); // for normal action
+            let dist = WeightedIndex::new(weights).unwrap();
+            let roll = dist.sample(state.rng.get_rng());
+            if roll != ready.len() {
+                // if not normal attack
+                let idx = ready[roll];
+                let skill = skilled.skills[idx].1.clone();
+                if skill.apply(entity, state.player_entity, state).is_some() {
+                    if let Ok(skilled) = state.ecs.query_one_mut::<&mut Skilled>(entity) {
+                        skilled.start_cooldown(idx);
+                    }
+                    return ACTION_COST;
                 }
             }
         }
     }
-    let (mon, viewer, pos) = state
+    let (mon, pos) = state
         .ecs
-        .query_one_mut::<(&mut Monster, Option<&mut Viewer>, &mut Position)>(entity)
+        .query_one_mut::<(&mut Monster, &Position)>(entity)
         .unwrap();
-    if target == None {
-        target = mon.tracking;
+    let here = pos.0;
+    let Some(target) = mon.tracking else {
+        return wander(state, entity);
+    };
+    if DistanceAlg::Chebyshev.distance2d(here, target) > SEARCH_RADIUS as f32 {
+        forget(mon);
+        return ACTION_COST;
     }
-    let Some(target) = target else { return };
-    let start = state.map.point2d_to_index(pos.0);
-    let end = state.map.point2d_to_index(target);
-    let path = a_star_search(start, end, &state.map);
-    if path.success && path.steps.len() > 1 {
-        let step_idx = path.steps[1];
-        let pt = state.map.index_to_point2d(step_idx);
+    let step = if target == player_pos {
+        // everyone chasing the player shares one map of the way there
+        state.flow.update(&state.map, &state.ecs, player_pos);
+        state.flow.next_step(&state.map, &state.ecs, here)
+    } else {
+        let start = state.map.point2d_to_index(here);
+        let end = state.map.point2d_to_index(target);
+        let area = SearchArea {
+            map: &state.map,
+            ecs: &state.ecs,
+            center: here,
+            radius: SEARCH_RADIUS,
+        };
+        let path = a_star_search(start, end, &area);
+        (path.success && path.steps.len() > 1).then(|| state.map.index_to_point2d(path.steps[1]))
+    };
+    if let Some(pt) = step {
+        let step_idx = state.map.point2d_to_index(pt);
         if pt == player_pos {
             let attack = state.ecs.query_one_mut::<Option<&Attack>>(entity).unwrap();
             if let Some(attack) = attack {
-                let damage = attack.damage;
-                let blocked = execute_defence_effects(state, entity);
-                let damage = (damage - blocked).abs();
-                let name = state.ecs.query_one_mut::<&Name>(entity).unwrap();
-                state
-                    .messages
-                    .enqueue_message(&format!("The {} hits you for {} damage.", name.0, damage));
-                let player_hp = state
-                    .ecs
-                    .query_one_mut::<&mut Health>(state.player_entity)
-                    .unwrap();
-                player_hp.hp -= damage;
+                let cost = attack.cost;
+                post(
+                    state,
+                    Event::Attack {
+                        attacker: entity,
+                        target: state.player_entity,
+                    },
+                );
+                return cost;
             }
         } else {
+            if state.map.tiles[step_idx].is_closed_door() {
+                open_door(state, entity, pt);
+                return ACTION_COST;
+            }
+            if let Some(other) = monster_at(&state.map, &state.ecs, step_idx) {
+                if DistanceAlg::Chebyshev.distance2d(pt, player_pos) <= 1.0 {
+                    // it's busy fighting, wait for a gap
+                    return ACTION_COST;
+                }
+                if !can_swap(state, other, here) {
+                    return ACTION_COST;
+                }
+                state.ecs.query_one_mut::<&mut Position>(other).unwrap().0 = here;
+                mark_moved(state, other);
+            } else if state.map.blocked_tiles[step_idx] {
+                return ACTION_COST;
+            }
+            let (mon, pos) = state
+                .ecs
+                .query_one_mut::<(&mut Monster, &mut Position)>(entity)
+                .unwrap();
             pos.0 = pt;
             if Some(pt) == mon.tracking {
-                mon.tracking = None;
-            }
-            if let Some(viewer) = viewer {
-                viewer.dirty = true;
+                // got there and found nothing
+                forget(mon);
             }
+            mark_moved(state, entity);
+            return state.map.move_cost(step_idx);
         }
     }
+    ACTION_COST
+}
+
+/// Whether `other` will trade places onto `to`: sleepers and bosses stay put, and nobody gets pushed into
+/// something that hurts them
+fn can_swap(state: &mut State, other: Entity, to: Point) -> bool {
+    let info = state.map.tiles[state.map.point2d_to_index(to)].info();
+    let query = state.ecs.query_one_mut::<(&Monster, Option<&Elemental>, Option<&Boss>)>(other);
+    let Ok((mon, element, boss)) = query else { return false };
+    mon.awareness != Awareness::Asleep
+        && boss.is_none()
+        && info.walkable
+        && (info.damage == 0 || info.element.as_ref() == element)
+}
+
+fn open_door(state: &mut State, entity: Entity, pt: Point) {
+    let idx = state.map.point2d_to_index(pt);
+    state.map.set_tile(idx, Tile::Door { open: true });
+    if state.map.visible_tiles[idx] {
+        let name = state.ecs.query_one_mut::<&Name>(entity).unwrap();
+        state
+            .messages
+            .enqueue_message(&format!("The {} opens a door.", name.0));
+    }
 }
 
-pub fn spawn_monster(state: &mut State, dl: i32, pos: Point) -> Entity {
+fn mark_moved(state: &mut State, entity: Entity) {
+    if let Ok(viewer) = state.ecs.query_one_mut::<&mut Viewer>(entity) {
+        viewer.dirty = true;
+    }
+}
+
+/// Idle monsters amble about, standing still about half the time
+fn wander(state: &mut State, entity: Entity) -> i32 {
+    if state.rng.range(0, 2) == 0 {
+        return ACTION_COST;
+    }
+    let pos = state.ecs.query_one_mut::<&Position>(entity).unwrap().0;
+    let player_idx = {
+        let player_pos = state.ecs.query_one_mut::<&Position>(state.player_entity).unwrap();
+        state.map.point2d_to_index(player_pos.0)
+    };
+    let idx = state.map.point2d_to_index(pos);
+    let mut exits = state.map.get_available_exits(idx);
+    // the player doesn't block, but nobody should stroll into them by accident
+    exits.retain(|(i, _)| *i != player_idx);
+    if exits.is_empty() {
+        return ACTION_COST;
+    }
+    let (dest, _) = exits[state.rng.range(0, exits.len())];
+    let dest = state.map.index_to_point2d(dest);
+    state.ecs.query_one_mut::<&mut Position>(entity).unwrap().0 = dest;
+    mark_moved(state, entity);
+    state.map.move_cost(state.map.point2d_to_index(dest))
+}
+
+/// monsters of the floor's element are this many times as likely to turn up as any other
+const ALIGNED_WEIGHT: i32 = 3;
+
+/// Picks a kind of monster for the current floor from the spawn table
+pub fn pick_monster(state: &mut State) -> Option<SpawnEntry> {
+    let raws = RAWS.lock().unwrap();
+    let aligned = |entry: &SpawnEntry| {
+        let aligned = state.map.element.as_ref().map_or(false, |element| {
+            lookup(&raws.monsters, entry.rank, &entry.name).map_or(false, |components| {
+                components
+                    .iter()
+                    .any(|c| matches!(c, Component::Elemental(e) if e == element))
+            })
+        });
+        if aligned {
+            ALIGNED_WEIGHT
+        } else {
+            1
+        }
+    };
+    pick(&raws.table.monsters, state.map.depth, aligned, &mut state.rng).cloned()
+}
+
+pub fn spawn_monster(state: &mut State, entry: &SpawnEntry, pos: Point) -> Entity {
+    spawn_monster_named(state, entry.rank, &entry.name, pos)
+}
+
+pub fn spawn_monster_named(state: &mut State, rank: i32, name: &str, pos: Point) -> Entity {
     let entity = state.ecs.spawn(());
     {
         let raws = RAWS.lock().unwrap();
-        let monsters = &raws.monsters[&dl];
-        let monster_of_choice = state.rng.range(0, monsters.len());
-        for component in monsters[monster_of_choice].iter() {
+        let components = lookup(&raws.monsters, rank, name)
+            .unwrap_or_else(|| panic!("No monster called {} at rank {}", name, rank));
+        for component in components.iter() {
             component.clone().insert(&mut state.ecs, entity).unwrap();
         }
     }
+    // some of the dungeon's residents are caught napping
+    let awareness = if state.rng.range(0, 3) == 0 {
+        Awareness::Asleep
+    } else {
+        Awareness::Idle
+    };
     state
         .ecs
         .insert(
             entity,
             (
-                Monster { tracking: None },
+                Monster::new(awareness),
                 Position(pos),
                 Ephermal,
                 Blocker {},
-                Rank(dl),
+                Rank(rank),
             ),
         )
         .unwrap();
@@ -117,12 +256,13 @@ pub fn spawn_monster_idx(state: &mut State, dl: i32, pos: Point, idx: usize) ->
             component.clone().insert(&mut state.ecs, entity).unwrap();
         }
     }
+    let awareness = Awareness::Idle;
     state
         .ecs
         .insert(
             entity,
             (
-                Monster { tracking: None },
+                Monster::new(awareness),
                 Position(pos),
                 Ephermal,
                 Blocker {},
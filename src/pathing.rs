@@ -0,0 +1,353 @@
+//! How monsters find their way. Other monsters aren't walls here, just costly to go through, so one stuck behind
+//! another still knows which way to go, and a pack fans out around the player rather than queueing up.
+//!
+//! Everything chasing the player follows one shared `FlowField`, anything heading elsewhere runs its own A* in a
+//! `SearchArea`.
+
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bracket_lib::prelude::*;
+use hecs::{Entity, World};
+
+use crate::{
+    components::Monster,
+    map::Map,
+    perception::SEARCH_RADIUS,
+};
+
+/// extra cost of a step through a tile another monster is standing on
+pub const CROWD_COST: f32 = 4.0;
+/// extra cost of a step through a closed door, for the turn spent opening it
+pub const DOOR_COST: f32 = 1.0;
+
+/// flow field distances are kept in tenths of a tile
+const STRAIGHT: i32 = 10;
+const DIAGONAL: i32 = 14;
+/// nothing further than this along the way from the player gets a distance
+const MAX_FLOW: i32 = SEARCH_RADIUS * 2 * STRAIGHT;
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (-1, 0),
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+pub fn monster_at(map: &Map, ecs: &World, idx: usize) -> Option<Entity> {
+    map.tile_contents[idx]
+        .iter()
+        .copied()
+        .find(|e| ecs.satisfies::<&Monster>(*e).unwrap_or(false))
+}
+
+/// The map as a monster standing at `center` sees it: nothing further than `radius` away, so searches stay
+/// bounded, and monsters in the way only cost extra
+pub struct SearchArea<'a> {
+    pub map: &'a Map,
+    pub ecs: &'a World,
+    pub center: Point,
+    pub radius: i32,
+}
+
+impl SearchArea<'_> {
+    fn in_area(&self, pt: Point) -> bool {
+        (pt.x - self.center.x).abs() <= self.radius && (pt.y - self.center.y).abs() <= self.radius
+    }
+}
+
+impl BaseMap for SearchArea<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
+    }
+    fn get_available_exits(&self, idx: usize) -> SmallVec<[(usize, f32); 10]> {
+        let mut exits = SmallVec::new();
+        let pt = self.map.index_to_point2d(idx);
+        for (dx, dy) in NEIGHBOURS {
+            let next = pt + Point::new(dx, dy);
+            if !self.map.in_bounds(next) || !self.in_area(next) {
+                continue;
+            }
+            let i = self.map.point2d_to_index(next);
+            let cost = if dx != 0 && dy != 0 { 1.4 } else { 1.0 };
+            let cost = cost * self.map.tiles[i].info().cost;
+            if !self.map.blocked_tiles[i] {
+                exits.push((i, cost));
+            } else if self.map.tiles[i].is_closed_door() {
+                exits.push((i, cost + DOOR_COST));
+            } else if monster_at(self.map, self.ecs, i).is_some() {
+                exits.push((i, cost + CROWD_COST));
+            }
+        }
+        exits
+    }
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
+    }
+}
+
+/// How far every tile is from the player, ignoring other monsters. It only gets rebuilt when the player moves or
+/// the walls or ground change (`Map::changes`), monsters moving about don't count.
+#[derive(Clone, Debug, Default)]
+pub struct FlowField {
+    goal: Option<Point>,
+    /// `Map::changes` when this was built
+    changes: u64,
+    distances: Vec<i32>,
+}
+
+/// Blocked by something monsters can't get through: not another monster, and not a door they could open
+fn is_wall(map: &Map, ecs: &World, idx: usize) -> bool {
+    map.blocked_tiles[idx] && !map.tiles[idx].is_closed_door() && monster_at(map, ecs, idx).is_none()
+}
+
+impl FlowField {
+    /// Rebuilds the field if it's out of date, returning whether it had to
+    pub fn update(&mut self, map: &Map, ecs: &World, goal: Point) -> bool {
+        if self.goal == Some(goal) && self.changes == map.changes {
+            return false;
+        }
+        let walls: Vec<bool> = (0..map.tiles.len())
+            .map(|i| is_wall(map, ecs, i))
+            .collect();
+        self.distances = vec![i32::MAX; walls.len()];
+        let start = map.point2d_to_index(goal);
+        self.distances[start] = 0;
+        let mut open = BinaryHeap::new();
+        open.push(Reverse((0, start)));
+        while let Some(Reverse((dist, idx))) = open.pop() {
+            if dist > self.distances[idx] {
+                continue;
+            }
+            let pt = map.index_to_point2d(idx);
+            for (dx, dy) in NEIGHBOURS {
+                let next = pt + Point::new(dx, dy);
+                if !map.in_bounds(next) {
+                    continue;
+                }
+                let next_idx = map.point2d_to_index(next);
+                if walls[next_idx] {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 { DIAGONAL } else { STRAIGHT };
+                // something at `next` pays for stepping onto `pt`, the field runs backwards from the player
+                let tile = map.tiles[idx];
+                let mut step = (step as f32 * tile.info().cost) as i32;
+                if tile.is_closed_door() {
+                    step += (DOOR_COST * STRAIGHT as f32) as i32;
+                }
+                let next_dist = dist + step;
+                if next_dist <= MAX_FLOW && next_dist < self.distances[next_idx] {
+                    self.distances[next_idx] = next_dist;
+                    open.push(Reverse((next_dist, next_idx)));
+                }
+            }
+        }
+        self.goal = Some(goal);
+        self.changes = map.changes;
+        true
+    }
+
+    pub fn distance(&self, map: &Map, pt: Point) -> Option<i32> {
+        let dist = *self.distances.get(map.point2d_to_index(pt))?;
+        (dist != i32::MAX).then_some(dist)
+    }
+
+    /// The best neighbouring tile that's closer to the goal, counting other monsters as `CROWD_COST`. `None` when
+    /// there's no way there from `from`.
+    pub fn next_step(&self, map: &Map, ecs: &World, from: Point) -> Option<Point> {
+        let here = self.distance(map, from)?;
+        let mut best: Option<(i32, Point)> = None;
+        for (dx, dy) in NEIGHBOURS {
+            let next = from + Point::new(dx, dy);
+            if !map.in_bounds(next) {
+                continue;
+            }
+            let Some(dist) = self.distance(map, next) else { continue };
+            if dist >= here {
+                continue;
+            }
+            let idx = map.point2d_to_index(next);
+            let mut score = dist;
+            if monster_at(map, ecs, idx).is_some() {
+                score += (CROWD_COST * STRAIGHT as f32) as i32;
+            }
+            if best.map_or(true, |(b, _)| score < b) {
+                best = Some((score, next));
+            }
+        }
+        best.map(|(_, pt)| pt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::*, map::Tile};
+
+    /// A corridor along y = 1 from x = 1 to x = 8, `width` tiles wide, with a monster standing in it at x = 4
+    fn corridor(width: i32) -> (Map, World) {
+        let mut map = Map::blank(0, 10, 2 + width);
+        for x in 1..9 {
+            for y in 1..1 + width {
+                let idx = map.point2d_to_index(Point::new(x, y));
+                map.tiles[idx] = Tile::Floor;
+            }
+        }
+        for (idx, tile) in map.tiles.iter().enumerate() {
+            map.blocked_tiles[idx] = *tile == Tile::Wall;
+        }
+        let mut ecs = World::new();
+        let pos = Point::new(4, 1);
+        let blocker = ecs.spawn((Monster::new(Awareness::Idle), Position(pos), Blocker {}));
+        let idx = map.point2d_to_index(pos);
+        map.blocked_tiles[idx] = true;
+        map.tile_contents[idx].push(blocker);
+        (map, ecs)
+    }
+
+    fn path(map: &Map, ecs: &World) -> NavigationPath {
+        let area = SearchArea {
+            map,
+            ecs,
+            center: Point::new(1, 1),
+            radius: 16,
+        };
+        let start = map.point2d_to_index(Point::new(1, 1));
+        let end = map.point2d_to_index(Point::new(8, 1));
+        a_star_search(start, end, &area)
+    }
+
+    #[test]
+    fn monsters_are_not_walls() {
+        let (map, ecs) = corridor(1);
+        let path = path(&map, &ecs);
+        assert!(path.success);
+        assert!(path.steps.contains(&map.point2d_to_index(Point::new(4, 1))));
+    }
+
+    #[test]
+    fn go_around_when_there_is_room() {
+        let (map, ecs) = corridor(2);
+        let path = path(&map, &ecs);
+        assert!(path.success);
+        assert!(!path.steps.contains(&map.point2d_to_index(Point::new(4, 1))));
+    }
+
+    #[test]
+    fn flow_field_follows_the_player() {
+        let (map, ecs) = corridor(2);
+        let mut flow = FlowField::default();
+        assert!(flow.update(&map, &ecs, Point::new(8, 1)));
+        assert!(!flow.update(&map, &ecs, Point::new(8, 1)));
+        // goes around the monster at (4, 1)
+        assert_eq!(
+            flow.next_step(&map, &ecs, Point::new(3, 1)),
+            Some(Point::new(4, 2))
+        );
+        assert!(flow.update(&map, &ecs, Point::new(7, 1)));
+        assert_eq!(flow.distance(&map, Point::new(7, 1)), Some(0));
+    }
+
+    #[test]
+    fn flow_field_redone_when_the_ground_changes() {
+        let (mut map, ecs) = corridor(2);
+        let mut flow = FlowField::default();
+        assert!(flow.update(&map, &ecs, Point::new(8, 1)));
+        let idx = map.point2d_to_index(Point::new(6, 1));
+        map.set_tile(idx, Tile::Wall);
+        map.blocked_tiles[idx] = true;
+        assert!(flow.update(&map, &ecs, Point::new(8, 1)));
+        assert_eq!(flow.distance(&map, Point::new(6, 1)), None);
+        assert!(!flow.update(&map, &ecs, Point::new(8, 1)));
+    }
+
+    #[test]
+    fn stays_in_radius() {
+        let (map, ecs) = corridor(1);
+        let area = SearchArea {
+            map: &map,
+            ecs: &ecs,
+            center: Point::new(1, 1),
+            radius: 3,
+        };
+        let start = map.point2d_to_index(Point::new(1, 1));
+        let end = map.point2d_to_index(Point::new(8, 1));
+        assert!(!a_star_search(start, end, &area).success);
+    }
+
+    /// A 70x60 floor like the ones the game makes, with a crowd of monsters all chasing the player. The shared
+    /// field gets rebuilt once per player move, where A* runs once per monster. It goes by the clock, so it's only
+    /// run when asked for: `cargo test --release -- --ignored flow_field_beats_a_star`
+    #[test]
+    #[ignore]
+    fn flow_field_beats_a_star() {
+        use std::time::{Duration, Instant};
+
+        let mut map = Map::blank(0, 70, 60);
+        for y in 1..59 {
+            for x in 1..69 {
+                // rows of pillars with gaps, so paths have to wind a little
+                let pillar = x % 6 == 0 && y % 5 != 0;
+                let idx = map.point2d_to_index(Point::new(x, y));
+                map.tiles[idx] = if pillar { Tile::Wall } else { Tile::Floor };
+            }
+        }
+        for (idx, tile) in map.tiles.iter().enumerate() {
+            map.blocked_tiles[idx] = *tile == Tile::Wall;
+        }
+        let mut ecs = World::new();
+        let mut monsters = vec![];
+        for i in 0..120 {
+            let pt = Point::new(1 + (i * 7) % 67, 1 + (i * 11) % 57);
+            let idx = map.point2d_to_index(pt);
+            if map.blocked_tiles[idx] {
+                continue;
+            }
+            let e = ecs.spawn((Monster::new(Awareness::Hunting), Position(pt), Blocker {}));
+            map.blocked_tiles[idx] = true;
+            map.tile_contents[idx].push(e);
+            monsters.push(pt);
+        }
+        let goals = [
+            Point::new(35, 30),
+            Point::new(36, 30),
+            Point::new(37, 31),
+            Point::new(37, 32),
+            Point::new(38, 32),
+        ];
+
+        let mut a_star_time = Duration::ZERO;
+        let mut flow_time = Duration::ZERO;
+        for goal in goals {
+            let start = Instant::now();
+            for pt in monsters.iter() {
+                let area = SearchArea {
+                    map: &map,
+                    ecs: &ecs,
+                    center: *pt,
+                    radius: SEARCH_RADIUS,
+                };
+                a_star_search(
+                    map.point2d_to_index(*pt),
+                    map.point2d_to_index(goal),
+                    &area,
+                );
+            }
+            a_star_time += start.elapsed();
+
+            let start = Instant::now();
+            let mut flow = FlowField::default();
+            flow.update(&map, &ecs, goal);
+            for pt in monsters.iter() {
+                flow.update(&map, &ecs, goal);
+                flow.next_step(&map, &ecs, *pt);
+            }
+            flow_time += start.elapsed();
+        }
+        assert!(flow_time < a_star_time, "a* {a_star_time:?}, flow field {flow_time:?}");
+    }
+}
@@ -0,0 +1,115 @@
+//! What monsters know about the player. A monster that sees the player hunts them, one that hears something goes
+//! to look, and one that has gone `MEMORY` turns without either gives up and wanders. Asleep monsters see nothing
+//! and only hear what's close.
+
+use bracket_lib::prelude::*;
+use hecs::Entity;
+
+use crate::{components::*, State};
+
+/// turns a monster keeps heading for somewhere it can't see
+pub const MEMORY: i32 = 10;
+/// monsters give up on anything further away than this
+pub const SEARCH_RADIUS: i32 = 16;
+
+/// how many tiles each kind of noise carries
+pub const MOVE_NOISE: i32 = 3;
+pub const ATTACK_NOISE: i32 = 7;
+pub const ARTIFACT_NOISE: i32 = 9;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Noise {
+    pub pos: Point,
+    pub volume: i32,
+}
+
+/// Noises last until the player's next action, so everyone who acts in between gets a chance to hear them
+pub fn make_noise(state: &mut State, pos: Point, volume: i32) {
+    state.noises.push(Noise { pos, volume });
+}
+
+fn hears(noise: &Noise, pos: Point, hearing: &Hearing, asleep: bool) -> bool {
+    let range = if asleep {
+        hearing.range / 2
+    } else {
+        hearing.range
+    };
+    DistanceAlg::Pythagoras.distance2d(pos, noise.pos) <= range.min(noise.volume) as f32
+}
+
+/// Updates what `entity` knows about the player, returning whether it can see them
+pub fn perceive(state: &mut State, entity: Entity, player_pos: Point) -> bool {
+    let Ok((mon, pos, viewer, hearing)) = state
+        .ecs
+        .query_one_mut::<(&mut Monster, &Position, Option<&Viewer>, Option<&Hearing>)>(entity)
+    else {
+        return false;
+    };
+    let asleep = mon.awareness == Awareness::Asleep;
+    if !asleep && viewer.map_or(false, |v| v.visible_tiles.contains(&player_pos)) {
+        mon.awareness = Awareness::Hunting;
+        mon.tracking = Some(player_pos);
+        mon.memory = MEMORY;
+        return true;
+    }
+    let heard = hearing.and_then(|hearing| {
+        state
+            .noises
+            .iter()
+            .rev()
+            .find(|n| hears(n, pos.0, hearing, asleep))
+    });
+    if let Some(noise) = heard {
+        mon.awareness = Awareness::Searching;
+        mon.tracking = Some(noise.pos);
+        mon.memory = MEMORY;
+        return false;
+    }
+    if mon.awareness == Awareness::Hunting {
+        mon.awareness = Awareness::Searching;
+    }
+    if mon.tracking.is_some() {
+        mon.memory -= 1;
+        if mon.memory <= 0 {
+            forget(mon);
+        }
+    }
+    false
+}
+
+pub fn forget(mon: &mut Monster) {
+    mon.tracking = None;
+    mon.memory = 0;
+    if mon.awareness != Awareness::Asleep {
+        mon.awareness = Awareness::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleepers_hear_less() {
+        let hearing = Hearing { range: 8 };
+        let noise = Noise {
+            pos: Point::new(0, 0),
+            volume: ATTACK_NOISE,
+        };
+        assert!(hears(&noise, Point::new(6, 0), &hearing, false));
+        assert!(!hears(&noise, Point::new(6, 0), &hearing, true));
+        assert!(!hears(&noise, Point::new(8, 0), &hearing, false));
+    }
+
+    #[test]
+    fn forgetting_wakes_nobody() {
+        let mut mon = Monster::new(Awareness::Searching);
+        mon.tracking = Some(Point::new(3, 3));
+        forget(&mut mon);
+        assert_eq!(mon.awareness, Awareness::Idle);
+        assert_eq!(mon.tracking, None);
+        let mut sleeper = Monster::new(Awareness::Asleep);
+        forget(&mut sleeper);
+        assert_eq!(sleeper.awareness, Awareness::Asleep);
+    }
+}
@@ -1,15 +1,20 @@
 use bracket_lib::prelude::*;
-use hecs::With;
 
 use crate::{
+    combat::{post, Event},
     components::*,
-    equipment::{build_blueprint, execute_attack_effects, EquipmentEffect},
+    dungeon,
+    equipment::{build_blueprint, Trigger},
     map,
     mapping::Command,
+    perception::{make_noise, ATTACK_NOISE, MOVE_NOISE},
+    save,
+    scheduler::ACTION_COST,
     ui, OperatingMode, State,
 };
 
-pub fn player_act(state: &mut State, command: &Command) -> bool {
+/// Returns the energy spent, or `None` if the player didn't use up their turn
+pub fn player_act(state: &mut State, command: &Command) -> Option<i32> {
     match *command {
         Command::Move { target: move_pt } => {
             let position = state
@@ -37,32 +42,68 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
                     }
                 }
                 if let Some(target) = found_target {
-                    let (health, name) = state
-                        .ecs
-                        .query_one_mut::<With<(&mut Health, &Name), &Monster>>(target)
-                        .unwrap();
-                    state.messages.enqueue_message(&format!(
-                        "You hit the {} for {} damage.",
-                        name.0, attacker.damage
-                    ));
-                    health.hp -= attacker.damage;
-                    execute_attack_effects(state, target);
-                    return true;
+                    post(
+                        state,
+                        Event::Attack {
+                            attacker: state.player_entity,
+                            target,
+                        },
+                    );
+                    make_noise(state, new_pt, ATTACK_NOISE);
+                    return Some(attacker.cost);
                 }
             }
+            if state.map.tiles[new_idx].is_closed_door() {
+                state.map.set_tile(new_idx, map::Tile::Door { open: true });
+                state.messages.enqueue_message("You open the door.");
+                return Some(ACTION_COST);
+            }
             if state.map.is_available_exit(new_idx) {
                 let position = state
                     .ecs
                     .query_one_mut::<&mut Position>(state.player_entity)
                     .unwrap();
                 position.0 = new_pt;
+                make_noise(state, new_pt, MOVE_NOISE);
                 if let Ok(viewer) = state.ecs.query_one_mut::<&mut Viewer>(state.player_entity) {
                     viewer.dirty = true;
                 }
-                true
+                Some(state.map.move_cost(new_idx))
             } else {
-                false
+                None
+            }
+        }
+        Command::OpenDoor => {
+            let doors = adjacent_doors(state, false);
+            if doors.is_empty() {
+                state
+                    .messages
+                    .enqueue_message("There's no closed door next to you.");
+                return None;
+            }
+            for idx in doors {
+                state.map.set_tile(idx, map::Tile::Door { open: true });
             }
+            state.messages.enqueue_message("You open the door.");
+            Some(ACTION_COST)
+        }
+        Command::CloseDoor => {
+            // nothing can be shut in a doorway
+            let doors: Vec<usize> = adjacent_doors(state, true)
+                .into_iter()
+                .filter(|idx| state.map.tile_contents[*idx].is_empty())
+                .collect();
+            if doors.is_empty() {
+                state
+                    .messages
+                    .enqueue_message("There's no open door you can close.");
+                return None;
+            }
+            for idx in doors {
+                state.map.set_tile(idx, map::Tile::Door { open: false });
+            }
+            state.messages.enqueue_message("You close the door.");
+            Some(ACTION_COST)
         }
         Command::Grab => {
             let position = state
@@ -94,9 +135,9 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
                 } else {
                     state.messages.enqueue_message("You pick something up.");
                 }
-                true
+                Some(ACTION_COST)
             } else {
-                false
+                None
             }
         }
         Command::OpenInventory => {
@@ -109,19 +150,19 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
                 length: inv.contents.len() as u32,
                 confirming: None,
             });
-            false
+            None
         }
         Command::OpenMessageLog => {
             state.operating_mode = OperatingMode::OpenMessageLog;
-            false
+            None
         }
         Command::OpenExamine => {
             state.operating_mode = OperatingMode::OpenExamine(ui::ExamineUIState {
                 point: Point::new(map::MAP_UI_DIM.width() / 2, map::MAP_UI_DIM.height() / 2),
             });
-            false
+            None
         }
-        Command::Wait => true,
+        Command::Wait => Some(ACTION_COST),
         Command::DescendStairs => {
             let player_pos = state
                 .ecs
@@ -130,35 +171,49 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
             let idx = state.map.point2d_to_index(player_pos.0);
             if state.map.tiles[idx] == map::Tile::Stairs {
                 state.messages.enqueue_message("You descend the stairs.");
-                map::new_floor(state);
-                return true;
+                dungeon::descend(state);
+                // the player starts the new floor with an empty tank, like everyone else
+                return Some(0);
+            }
+            None
+        }
+        Command::AscendStairs => {
+            let player_pos = state
+                .ecs
+                .query_one_mut::<&Position>(state.player_entity)
+                .unwrap();
+            let idx = state.map.point2d_to_index(player_pos.0);
+            if state.map.tiles[idx] == map::Tile::UpStairs {
+                state.messages.enqueue_message("You climb the stairs.");
+                dungeon::ascend(state);
+                return Some(0);
             }
-            false
+            None
         }
         Command::CreateItem => {
             let p = state
                 .ecs
                 .query_one_mut::<&mut Player>(state.player_entity)
                 .unwrap();
-            let Some(bp) = &p.current_blueprint else { return false };
+            let Some(bp) = &p.current_blueprint else { return None };
             if bp.filled.len() == bp.img.lookup().gem_spots.len() {
                 let thing = build_blueprint(bp);
-                match thing.effect {
-                    EquipmentEffect::Active(_) => p.active_equipment.push(Some(thing)),
-                    EquipmentEffect::Passive(_) => p.passive_equipment.push(Some(thing)),
+                match thing.trigger() {
+                    Trigger::Targeted => p.active_equipment.push(Some(thing)),
+                    Trigger::OnAttack | Trigger::OnHit => p.passive_equipment.push(Some(thing)),
                 }
                 state
                     .messages
                     .enqueue_message(&format!("You forge a {:?}!", bp.equipment));
                 p.current_blueprint = None;
-                return true;
+                return Some(ACTION_COST);
             }
             state
                 .messages
                 .enqueue_message("Slots not full: cannot forge yet.");
-            false
+            None
         }
-        Command::UseActive(action_idx) => {
+        Command::Number(action_idx) if action_idx >= 1 => {
             let p = state
                 .ecs
                 .query_one_mut::<&mut Player>(state.player_entity)
@@ -174,7 +229,7 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
                     equipment: action_idx as usize - 1,
                 };
             }
-            false
+            None
         }
         Command::EquipExamine => {
             let player = state
@@ -186,12 +241,44 @@ pub fn player_act(state: &mut State, command: &Command) -> bool {
                 selection: 0,
                 length: length as i32,
             });
-            false
+            None
         }
         Command::GetHelp => {
             state.operating_mode = OperatingMode::HelpMenu;
-            false
+            None
+        }
+        Command::SaveGame => {
+            match save::save_to_file(state) {
+                Ok(()) => state.messages.enqueue_message("Game saved."),
+                Err(e) => state
+                    .messages
+                    .enqueue_message(&format!("Could not save the game: {}", e)),
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Indices of the doors around the player that are `open`, or closed
+fn adjacent_doors(state: &mut State, open: bool) -> Vec<usize> {
+    let pos = state
+        .ecs
+        .query_one_mut::<&Position>(state.player_entity)
+        .unwrap()
+        .0;
+    let mut doors = vec![];
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let pt = pos + Point::new(dx, dy);
+            if !state.map.in_bounds(pt) {
+                continue;
+            }
+            let idx = state.map.point2d_to_index(pt);
+            if state.map.tiles[idx] == (map::Tile::Door { open }) {
+                doors.push(idx);
+            }
         }
-        _ => false,
     }
+    doors
 }
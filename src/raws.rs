@@ -1,33 +1,212 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
 
 use bracket_lib::prelude::*;
 use lazy_static::lazy_static;
 use serde::Deserialize;
 
-use crate::components_serde::Component;
+use crate::{
+    components_serde::Component,
+    equipment::{EquipmentDef, EquipmentType},
+    map_builders::{
+        prefab::{xp_template, PrefabDef},
+        LevelDef,
+    },
+    spawn_table::SpawnTable,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct Raws {
     pub monsters: HashMap<i32, Vec<Vec<Component>>>, // no need to manually add `Monster` component
     pub items: HashMap<i32, Vec<Vec<Component>>>,    // same as above, but with `Item` component
+    /// which of the above turn up on which depths, and how many
+    #[serde(default)]
+    pub table: SpawnTable,
+    /// loaded from its own file, see `equipment.json`
+    #[serde(skip)]
+    pub equipment: HashMap<EquipmentType, EquipmentDef>,
+    /// loaded from `levels.json`, each depth's element and the ways it can be laid out
+    #[serde(skip)]
+    pub levels: HashMap<i32, LevelDef>,
+    /// loaded from `prefabs.json`, with any drawn in REXPaint read into templates
+    #[serde(skip)]
+    pub prefabs: HashMap<String, PrefabDef>,
+    /// what the help screen says, from `help.txt`
+    #[serde(skip)]
+    pub help: String,
 }
 
+pub mod check;
+
+/// the monsters under this rank are the final bosses, placed by the last floor's prefab rather than the spawn table
+pub const BOSS_RANK: i32 = 666;
+
 embedded_resource!(RAW_FILE, "../raws/spawns.json");
+embedded_resource!(HELP_FILE, "../raws/help.txt");
+embedded_resource!(EQUIPMENT_FILE, "../raws/equipment.json");
+embedded_resource!(LEVELS_FILE, "../raws/levels.json");
+embedded_resource!(PREFABS_FILE, "../raws/prefabs.json");
+embedded_resource!(FIRE_SHRINE_XP, "../assets/prefabs/fire-shrine.xp");
 
 lazy_static! {
     pub static ref RAWS: Mutex<Raws> = Mutex::new(Raws {
         monsters: HashMap::new(),
         items: HashMap::new(),
+        table: SpawnTable::default(),
+        equipment: HashMap::new(),
+        levels: HashMap::new(),
+        prefabs: HashMap::new(),
+        help: String::new(),
     });
+    static ref RAW_DIR: Mutex<Option<RawDir>> = Mutex::new(None);
 }
 
-pub fn load_raws() {
+/// the raws that can be read from a directory on disk instead, and get reloaded when they change there
+const RELOADABLE: [&str; 2] = ["spawns.json", "help.txt"];
+
+/// how often the files in a raws directory get looked at for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the game was told to read raws from with `--raws`
+struct RawDir {
+    path: PathBuf,
+    /// when each of `RELOADABLE` was last changed, as of the last look
+    modified: Vec<Option<SystemTime>>,
+    last_checked: Instant,
+}
+
+impl RawDir {
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        RELOADABLE
+            .iter()
+            .map(|file| fs::metadata(self.path.join(file)).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
+
+/// Reads the reloadable raws from `path` from now on, for anything it has a copy of. Has to be called before
+/// `load_raws`.
+pub fn use_raw_dir(path: PathBuf) {
+    let mut dir = RawDir {
+        path,
+        modified: vec![],
+        last_checked: Instant::now(),
+    };
+    dir.modified = dir.modified();
+    *RAW_DIR.lock().unwrap() = Some(dir);
+}
+
+/// The contents of one of the raws, from the raws directory if there is one and it has it
+fn raw_string(file: &str) -> String {
+    if RELOADABLE.contains(&file) {
+        if let Some(dir) = RAW_DIR.lock().unwrap().as_ref() {
+            if let Ok(contents) = fs::read_to_string(dir.path.join(file)) {
+                return contents;
+            }
+        }
+    }
+    embedded_string(&format!("../raws/{file}"))
+}
+
+fn embedded_string(path: &str) -> String {
+    let data = EMBED.lock().get_resource(path.to_string()).unwrap();
+    String::from_utf8(data.to_vec()).expect("Unable to convert to a valid UTF-8 string.")
+}
+
+fn link_raws() {
     link_resource!(RAW_FILE, "../raws/spawns.json");
-    let data = EMBED
-        .lock()
-        .get_resource("../raws/spawns.json".to_string())
-        .unwrap();
-    let string = std::str::from_utf8(&data).expect("Unable to convert to a valid UTF-8 string.");
-    let raws: Raws = serde_json::from_str(string).expect("Unable to parse json");
-    *RAWS.lock().unwrap() = raws;
+    link_resource!(HELP_FILE, "../raws/help.txt");
+    link_resource!(EQUIPMENT_FILE, "../raws/equipment.json");
+    link_resource!(LEVELS_FILE, "../raws/levels.json");
+    link_resource!(PREFABS_FILE, "../raws/prefabs.json");
+    link_resource!(FIRE_SHRINE_XP, "../assets/prefabs/fire-shrine.xp");
+}
+
+/// Everything wrong with the raws, see `check`
+pub fn check_raws() -> Vec<check::Problem> {
+    link_raws();
+    check::check(&check::RawFiles {
+        spawns: &raw_string("spawns.json"),
+        equipment: &raw_string("equipment.json"),
+        levels: &raw_string("levels.json"),
+        prefabs: &raw_string("prefabs.json"),
+    })
+}
+
+/// The raws as they are now, or what's wrong with them
+fn read_raws() -> Result<Raws, Vec<check::Problem>> {
+    let problems = check_raws();
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    let mut raws: Raws =
+        serde_json::from_str(&raw_string("spawns.json")).expect("Unable to parse json");
+    raws.equipment = serde_json::from_str(&raw_string("equipment.json"))
+        .expect("Unable to parse equipment json");
+    raws.levels = serde_json::from_str(&raw_string("levels.json"))
+        .expect("Unable to parse levels json");
+    raws.prefabs = load_prefabs();
+    raws.help = raw_string("help.txt");
+    Ok(raws)
+}
+
+pub fn load_raws() {
+    match read_raws() {
+        Ok(raws) => *RAWS.lock().unwrap() = raws,
+        Err(problems) => {
+            let list: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+            panic!("The raws have problems:\n{}", list.join("\n"));
+        }
+    }
+}
+
+/// Reloads the raws if any in the raws directory have changed since last time, returning whether they were.
+/// Whatever has already been spawned keeps the components it was made with. If the new raws have problems
+/// they're printed and the old ones are kept, so a half-saved file can't take the game down.
+pub fn reload_changed_raws() -> bool {
+    {
+        let mut dir = RAW_DIR.lock().unwrap();
+        let Some(dir) = dir.as_mut() else { return false };
+        if dir.last_checked.elapsed() < RELOAD_INTERVAL {
+            return false;
+        }
+        dir.last_checked = Instant::now();
+        let modified = dir.modified();
+        if modified == dir.modified {
+            return false;
+        }
+        dir.modified = modified;
+    }
+    match read_raws() {
+        Ok(raws) => {
+            *RAWS.lock().unwrap() = raws;
+            true
+        }
+        Err(problems) => {
+            for problem in problems {
+                eprintln!("{}", problem);
+            }
+            false
+        }
+    }
+}
+
+/// Prefabs drawn in REXPaint have to be linked here as well as named in `prefabs.json`
+pub fn load_prefabs() -> HashMap<String, PrefabDef> {
+    link_raws();
+    let mut prefabs: HashMap<String, PrefabDef> =
+        serde_json::from_str(&embedded_string("../raws/prefabs.json"))
+            .expect("Unable to parse prefabs json");
+    for def in prefabs.values_mut() {
+        if let Some(path) = &def.xp {
+            let xp = XpFile::from_resource(path).expect("Unable to load prefab image");
+            def.template = xp_template(&xp);
+        }
+    }
+    prefabs
 }
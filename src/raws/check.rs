@@ -0,0 +1,443 @@
+//! Looks over the raws for mistakes that would otherwise only show up as a panic partway through a game, and says
+//! where each one is. `load_raws` won't go on if there are any, and `--check-raws` lists them all.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use bracket_lib::prelude::*;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::BOSS_RANK;
+use crate::{
+    components_serde::Component,
+    equipment::{EquipmentDef, EquipmentType},
+    map::Spawn,
+    map_builders::{
+        prefab::{xp_template, Cell, PrefabDef},
+        BuilderSpec, LevelDef,
+    },
+    skill::Skill,
+    spawn_table::{SpawnEntry, SpawnTable},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// the file and the way into it, like `spawns.json/monsters/0/2`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// The contents of each raws file
+pub struct RawFiles<'a> {
+    pub spawns: &'a str,
+    pub equipment: &'a str,
+    pub levels: &'a str,
+    pub prefabs: &'a str,
+}
+
+/// What a kind of spawn has to come with, and what spawning it adds so it mustn't have already
+struct KindRules {
+    required: &'static [&'static str],
+    added: &'static [&'static str],
+}
+
+const MONSTER_RULES: KindRules = KindRules {
+    required: &["Name", "Health", "Attack", "Renderable"],
+    added: &["Position", "Monster", "Ephermal", "Rank"],
+};
+
+const ITEM_RULES: KindRules = KindRules {
+    required: &["Name", "Renderable", "Blueprint"],
+    added: &["Position", "Item", "Ephermal", "Rank"],
+};
+
+#[derive(Default)]
+struct Checker {
+    problems: Vec<Problem>,
+    /// where a skill summons something, and the rank and name of what it summons
+    summons: Vec<(String, i32, String)>,
+}
+
+impl Checker {
+    fn report(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.problems.push(Problem {
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn parse<T: DeserializeOwned>(&mut self, path: &str, json: &str) -> Option<T> {
+        serde_json::from_str(json)
+            .map_err(|e| self.report(path, e.to_string()))
+            .ok()
+    }
+
+    fn parse_value<T: DeserializeOwned>(&mut self, path: &str, value: &Value) -> Option<T> {
+        serde_json::from_value(value.clone())
+            .map_err(|e| self.report(path, e.to_string()))
+            .ok()
+    }
+
+    /// Goes through one of the rank-keyed lists in `spawns.json`, returning the names in each rank
+    fn spawn_list(
+        &mut self,
+        path: &str,
+        value: Option<&Value>,
+        rules: &KindRules,
+    ) -> HashMap<i32, Vec<String>> {
+        let mut names = HashMap::new();
+        let Some(ranks) = value.and_then(Value::as_object) else {
+            self.report(path, "missing, or not an object of ranks");
+            return names;
+        };
+        for (rank, entries) in ranks {
+            let rank_path = format!("{path}/{rank}");
+            let Ok(rank) = rank.parse::<i32>() else {
+                self.report(rank_path, "rank isn't a number");
+                continue;
+            };
+            let Some(entries) = entries.as_array() else {
+                self.report(rank_path, "not a list of entries");
+                continue;
+            };
+            if entries.is_empty() {
+                self.report(&rank_path, "empty rank, nothing could be spawned from it");
+            }
+            let bucket: &mut Vec<String> = names.entry(rank).or_default();
+            for (i, entry) in entries.iter().enumerate() {
+                let entry_path = format!("{rank_path}/{i}");
+                let name = self.spawn_entry(&entry_path, entry, rules);
+                let Some(name) = name else { continue };
+                if bucket.contains(&name) {
+                    self.report(
+                        &entry_path,
+                        format!("another {name} is already in this rank"),
+                    );
+                }
+                bucket.push(name);
+            }
+        }
+        names
+    }
+
+    /// Checks an entry's components one at a time, so a bad one says which it is, and returns its name
+    fn spawn_entry(&mut self, path: &str, entry: &Value, rules: &KindRules) -> Option<String> {
+        let Some(components) = entry.as_array() else {
+            self.report(path, "not a list of components");
+            return None;
+        };
+        let mut kinds = HashSet::new();
+        let mut name = None;
+        for (i, component) in components.iter().enumerate() {
+            let kind = match component {
+                Value::Object(map) if map.len() == 1 => map.keys().next().unwrap().clone(),
+                Value::String(unit) => unit.clone(),
+                _ => {
+                    self.report(format!("{path}/{i}"), "not a component");
+                    continue;
+                }
+            };
+            let component_path = format!("{path}/{i} ({kind})");
+            match self.parse_value(&component_path, component) {
+                Some(Component::Name(n)) => name = Some(n.0),
+                Some(Component::Skilled(skilled)) => self.skills(&component_path, skilled.skills),
+                Some(Component::Resistances(r)) if r.0.values().any(|p| p.abs() > 100) => {
+                    self.report(&component_path, "percentages go from -100 to 100");
+                }
+                Some(Component::Defence(d)) if d.armor < 0 => {
+                    self.report(&component_path, "armor can't be negative");
+                }
+                Some(Component::Defence(d))
+                    if !(0..=100).contains(&d.block) || !(0..=100).contains(&d.evasion) =>
+                {
+                    self.report(&component_path, "chances go from 0 to 100");
+                }
+                Some(Component::Boss(boss)) => {
+                    if boss.phases.is_empty() {
+                        self.report(&component_path, "needs at least one phase");
+                    }
+                    if boss.phases.windows(2).any(|w| w[0].below <= w[1].below) {
+                        self.report(&component_path, "phases go from the most health down");
+                    }
+                    for phase in boss.phases {
+                        self.skills(&component_path, phase.skills);
+                    }
+                }
+                Some(Component::Speed(speed)) if speed.0 <= 0 => {
+                    self.report(&component_path, "has to be above 0");
+                }
+                Some(Component::Attack(attack)) if attack.cost <= 0 => {
+                    self.report(&component_path, "cost has to be above 0");
+                }
+                _ => {}
+            }
+            if rules.added.contains(&kind.as_str()) {
+                self.report(&component_path, "gets added when spawned, leave it out");
+            }
+            if !kinds.insert(kind.clone()) {
+                self.report(&component_path, "more than one of these");
+            }
+        }
+        for required in rules.required {
+            if !kinds.contains(*required) {
+                self.report(path, format!("missing {required}"));
+            }
+        }
+        name
+    }
+
+    /// Notes what the skills summon, to check once every monster is known
+    fn skills(&mut self, path: &str, skills: Vec<(f32, Skill)>) {
+        for (_, skill) in skills {
+            if let Skill::Summon { name, rank, .. } = skill {
+                self.summons.push((path.to_string(), rank, name));
+            }
+        }
+    }
+
+    fn table(&mut self, path: &str, entries: &[SpawnEntry], names: &HashMap<i32, Vec<String>>) {
+        for (i, entry) in entries.iter().enumerate() {
+            let path = format!("{path}/{i} ({})", entry.name);
+            if !names
+                .get(&entry.rank)
+                .map_or(false, |n| n.contains(&entry.name))
+            {
+                self.report(
+                    &path,
+                    format!("nothing called {} in rank {}", entry.name, entry.rank),
+                );
+            }
+            if entry.rank == BOSS_RANK {
+                self.report(&path, "bosses only turn up where prefabs put them");
+            }
+            if entry.weight <= 0 {
+                self.report(&path, "weight has to be more than 0");
+            }
+            if entry.depths.0 > entry.depths.1 {
+                self.report(&path, "depths are the wrong way round");
+            }
+            if entry.group.0 == 0 || entry.group.0 > entry.group.1 {
+                self.report(&path, "group has to be at least 1, smallest first");
+            }
+        }
+    }
+}
+
+/// Everything wrong with the raws, empty if nothing is
+pub fn check(files: &RawFiles) -> Vec<Problem> {
+    let mut checker = Checker::default();
+
+    let boss_count = match checker.parse::<Value>("spawns.json", files.spawns) {
+        Some(spawns) => {
+            let monsters = checker.spawn_list(
+                "spawns.json/monsters",
+                spawns.get("monsters"),
+                &MONSTER_RULES,
+            );
+            let items = checker.spawn_list("spawns.json/items", spawns.get("items"), &ITEM_RULES);
+            for (path, rank, name) in std::mem::take(&mut checker.summons) {
+                if !monsters.get(&rank).map_or(false, |n| n.contains(&name)) {
+                    checker.report(path, format!("summons {name}, which isn't in rank {rank}"));
+                }
+            }
+            let table: Option<SpawnTable> = match spawns.get("table") {
+                Some(table) => checker.parse_value("spawns.json/table", table),
+                None => {
+                    checker.report("spawns.json/table", "missing, nothing would spawn");
+                    None
+                }
+            };
+            if let Some(table) = table {
+                checker.table("spawns.json/table/monsters", &table.monsters, &monsters);
+                checker.table("spawns.json/table/items", &table.items, &items);
+                let mut depths: Vec<_> = table.counts.iter().collect();
+                depths.sort_by_key(|(depth, _)| **depth);
+                for (depth, counts) in depths {
+                    let path = format!("spawns.json/table/counts/{depth}");
+                    if counts.monsters > 0 && !table.monsters.iter().any(|e| e.allowed_on(*depth)) {
+                        checker.report(&path, "wants monsters but none are allowed this deep");
+                    }
+                    if counts.items > 0 && !table.items.iter().any(|e| e.allowed_on(*depth)) {
+                        checker.report(&path, "wants items but none are allowed this deep");
+                    }
+                }
+            }
+            let bosses = monsters.get(&BOSS_RANK).map_or(0, Vec::len);
+            if bosses == 0 {
+                checker.report(
+                    format!("spawns.json/monsters/{BOSS_RANK}"),
+                    "no bosses for the last floor",
+                );
+            }
+            bosses
+        }
+        None => 0,
+    };
+
+    checker.parse::<HashMap<EquipmentType, EquipmentDef>>("equipment.json", files.equipment);
+
+    let prefabs: HashMap<String, PrefabDef> = checker
+        .parse("prefabs.json", files.prefabs)
+        .unwrap_or_default();
+    let mut names: Vec<&String> = prefabs.keys().collect();
+    names.sort();
+    for name in names {
+        let path = format!("prefabs.json/{name}");
+        let mut def = prefabs[name].clone();
+        if let Some(xp) = &def.xp {
+            match XpFile::from_resource(xp) {
+                Ok(xp) => def.template = xp_template(&xp),
+                Err(_) => {
+                    checker.report(&path, format!("can't load {xp}, is it linked in raws.rs?"));
+                    continue;
+                }
+            }
+        }
+        if def.template.is_empty() {
+            checker.report(&path, "no template");
+        }
+        match def.cells() {
+            Ok(cells) => {
+                for cell in cells.iter().flatten() {
+                    if let Cell::Spawn(Spawn::Boss(idx)) = cell {
+                        if *idx >= boss_count {
+                            checker.report(&path, format!("there's no boss {}", idx + 1));
+                        }
+                    }
+                }
+            }
+            Err(c) => checker.report(&path, format!("unknown glyph {c:?}")),
+        }
+    }
+
+    let levels: HashMap<i32, LevelDef> = checker
+        .parse("levels.json", files.levels)
+        .unwrap_or_default();
+    let mut depths: Vec<_> = levels.iter().collect();
+    depths.sort_by_key(|(depth, _)| **depth);
+    for (depth, def) in depths {
+        if def.layouts.is_empty() {
+            checker.report(format!("levels.json/{depth}"), "no layouts");
+        }
+        for (i, layout) in def.layouts.iter().enumerate() {
+            let path = format!("levels.json/{depth}/layouts/{i}");
+            if layout.weight <= 0 {
+                checker.report(&path, "weight has to be more than 0");
+            }
+            for spec in layout.builders.iter() {
+                if let BuilderSpec::Prefab(name) = spec {
+                    if !prefabs.contains_key(name) {
+                        checker.report(&path, format!("no prefab called {name}"));
+                    }
+                }
+            }
+        }
+    }
+
+    checker.problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raws::check_raws;
+
+    #[test]
+    fn shipped_raws_are_fine() {
+        let problems = check_raws();
+        assert!(problems.is_empty(), "{problems:#?}");
+    }
+
+    fn spawn_problems(spawns: &str) -> Vec<String> {
+        check(&RawFiles {
+            spawns,
+            equipment: "{}",
+            levels: "{}",
+            prefabs: "{}",
+        })
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn reports_where_things_are() {
+        let problems = spawn_problems(
+            r##"{
+                "monsters": {
+                    "0": [
+                        [{ "Health": { "max_hp": 3 } }, { "Attack": { "damage": 1 } },
+                         { "Renderable": { "glyph": "z", "fg": "#ffffff", "bg": "#000000", "layer": 1 } }],
+                        [{ "Name": "Imp" }, { "Health": { "max_hp": 3 } }, { "Attack": { "damage": 1 } },
+                         { "Renderable": { "glyph": "i", "fg": "#ffffff", "bg": "#000000", "layer": 1 } },
+                         { "Skilled": { "skills": [[1.0, "Fireball"]] } }],
+                        [{ "Name": "Imp" }, { "Health": { "max_hp": 3 } }, { "Attack": { "damage": 1 } },
+                         { "Renderable": { "glyph": "i", "fg": "#ffffff", "bg": "#000000", "layer": 1 } },
+                         { "Skilled": { "skills": [[1.0, { "Summon": { "name": "Gremlin", "rank": 0 } }]] } }]
+                    ],
+                    "1": []
+                },
+                "items": {},
+                "table": {
+                    "monsters": [{ "name": "Gremlin", "rank": 0, "depths": [0, 1] }],
+                    "items": [],
+                    "counts": { "0": { "monsters": 5, "items": 1 } }
+                }
+            }"##,
+        );
+        let has = |start: &str, needle: &str| {
+            problems
+                .iter()
+                .any(|p| p.starts_with(start) && p.contains(needle))
+        };
+        assert!(
+            has("spawns.json/monsters/0/0:", "missing Name"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/monsters/0/1/4 (Skilled):", "Fireball"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/monsters/0/2:", "another Imp"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/monsters/0/2/4 (Skilled):", "summons Gremlin"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/monsters/1:", "empty rank"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/table/monsters/0 (Gremlin):", "nothing called"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/table/counts/0:", "wants items"),
+            "{problems:#?}"
+        );
+        assert!(
+            has("spawns.json/monsters/666:", "no bosses"),
+            "{problems:#?}"
+        );
+    }
+
+    #[test]
+    fn bad_json_says_where() {
+        let problems = spawn_problems("{ \"monsters\": ");
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("spawns.json: "));
+        assert!(problems[0].contains("line 1"));
+    }
+}
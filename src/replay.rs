@@ -0,0 +1,173 @@
+//! Recording the commands of a run and playing them back. A replay file is json lines: a header with the run
+//! seed, then one `Command` per line. Lines are written as they happen, so a crashed run still leaves a
+//! replay that leads right up to the crash. A run continued from a save is recorded from there on, with the
+//! save in the header to start the replay from.
+//!
+//! Saving isn't recorded, so watching a replay never overwrites the save.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::mapping::Command;
+
+pub const REPLAY_PATH: &str = "replay.jsonl";
+
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    seed: u64,
+    #[serde(default)]
+    save: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    /// the saved game the recording started from, if it didn't start with a new run
+    pub save: Option<String>,
+    pub commands: Vec<Command>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> io::Result<Replay> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+        let header = lines
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty replay file"))?;
+        let header: ReplayHeader = serde_json::from_str(header)?;
+        let mut commands = vec![];
+        for line in lines {
+            commands.push(serde_json::from_str(line)?);
+        }
+        Ok(Replay {
+            seed: header.seed,
+            save: header.save,
+            commands,
+        })
+    }
+}
+
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    /// `save` is what `save::save_game` made of the run as it is now, when it isn't starting from scratch
+    pub fn create(path: &str, seed: u64, save: Option<String>) -> io::Result<Recorder> {
+        let mut file = File::create(path)?;
+        writeln!(file, "{}", serde_json::to_string(&ReplayHeader { seed, save })?)?;
+        Ok(Recorder { file })
+    }
+    pub fn record(&mut self, command: &Command) {
+        if *command == Command::SaveGame {
+            return;
+        }
+        // a broken replay shouldn't take the game down with it
+        let _ = writeln!(self.file, "{}", serde_json::to_string(command).unwrap());
+    }
+}
+
+pub struct Playback {
+    pub commands: VecDeque<Command>,
+    /// 0 is paused, otherwise `speed * speed` commands a second
+    pub speed: i32,
+    since_last: f32,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            // older recordings still have saves in them
+            commands: replay
+                .commands
+                .into_iter()
+                .filter(|c| *c != Command::SaveGame)
+                .collect(),
+            speed: 3,
+            since_last: 0.,
+        }
+    }
+    /// Keys don't play the game while a replay runs: 0 pauses, 1-9 set the speed and enter steps once
+    pub fn control(&mut self, command: Command) -> Option<Command> {
+        match command {
+            Command::Number(n) => self.speed = n,
+            Command::Select if self.speed == 0 => return self.commands.pop_front(),
+            _ => {}
+        }
+        None
+    }
+    /// The commands that are due after `frame_time_ms` more milliseconds
+    pub fn advance(&mut self, frame_time_ms: f32) -> Vec<Command> {
+        if self.speed == 0 {
+            self.since_last = 0.;
+            return vec![];
+        }
+        let interval = 1000. / (self.speed * self.speed) as f32;
+        self.since_last += frame_time_ms;
+        let mut due = vec![];
+        while self.since_last >= interval {
+            self.since_last -= interval;
+            if let Some(command) = self.commands.pop_front() {
+                due.push(command);
+            }
+        }
+        due
+    }
+    pub fn finished(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bracket_lib::prelude::Point;
+
+    use super::*;
+
+    #[test]
+    fn record_and_load() {
+        let path = std::env::temp_dir().join("floyza-7drl-replay-test.jsonl");
+        let path = path.to_str().unwrap();
+        let commands = vec![
+            Command::Move {
+                target: Point::new(1, -1),
+            },
+            Command::Grab,
+            Command::SaveGame,
+            Command::Number(3),
+        ];
+        let mut recorder = Recorder::create(path, 1234, Some("{}".to_string())).unwrap();
+        for command in commands.iter() {
+            recorder.record(command);
+        }
+        drop(recorder);
+        let replay = Replay::load(path).unwrap();
+        assert_eq!(replay.seed, 1234);
+        assert_eq!(replay.save.as_deref(), Some("{}"));
+        // saving is left out
+        assert_eq!(replay.commands.len(), commands.len() - 1);
+        assert!(!replay.commands.contains(&Command::SaveGame));
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn playback_speed() {
+        let mut playback = Playback::new(Replay {
+            seed: 0,
+            save: None,
+            commands: vec![Command::Wait; 10],
+        });
+        playback.control(Command::Number(2));
+        assert_eq!(playback.advance(100.).len(), 0);
+        assert_eq!(playback.advance(400.).len(), 2);
+        playback.control(Command::Number(0));
+        assert_eq!(playback.advance(10000.).len(), 0);
+        assert_eq!(playback.control(Command::Select), Some(Command::Wait));
+        assert_eq!(playback.commands.len(), 7);
+    }
+}
@@ -0,0 +1,189 @@
+//! Saving and loading a whole run. Entities are stored row by row as their list of components, the
+//! same way `debug` dumps them, and forged equipment gets re-forged from its ingredients on load.
+
+use std::{fs, io};
+
+use bracket_lib::prelude::*;
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    combat::{Events, Stats},
+    components_serde::Component,
+    debug::get_entity_components,
+    dungeon::Dungeon,
+    map::Map,
+    messages::MessageLog,
+    pathing::FlowField,
+    scheduler::Scheduler,
+    OperatingMode, State,
+};
+
+pub const SAVE_PATH: &str = "savegame.json";
+
+#[derive(Serialize, Deserialize)]
+struct SaveData {
+    entities: Vec<(Entity, Vec<Component>)>,
+    map: Map,
+    #[serde(default)]
+    dungeon: Dungeon,
+    player_entity: Entity,
+    turn_order: Scheduler,
+    messages: MessageLog,
+    rng: RandomNumberGenerator,
+    seed: u64,
+    #[serde(default)]
+    stats: Stats,
+}
+
+pub fn save_game(state: &State) -> String {
+    let mut entities: Vec<(Entity, Vec<Component>)> = state
+        .ecs
+        .iter()
+        .map(|e| (e.entity(), get_entity_components(e)))
+        .collect();
+    // archetype iteration order isn't stable across a reload, entity ids are
+    entities.sort_by_key(|(e, _)| e.to_bits());
+    let data = SaveData {
+        entities,
+        map: state.map.clone(),
+        dungeon: state.dungeon.clone(),
+        player_entity: state.player_entity,
+        turn_order: state.turn_order.clone(),
+        messages: state.messages.clone(),
+        rng: state.rng.clone(),
+        seed: state.seed,
+        stats: state.stats.clone(),
+    };
+    serde_json::to_string(&data).unwrap()
+}
+
+/// Debug mode and recording aren't part of a run, so they're left off for the caller to set
+pub fn load_game(json: &str) -> serde_json::Result<State> {
+    let data: SaveData = serde_json::from_str(json)?;
+    let mut ecs = World::new();
+    for (entity, components) in data.entities {
+        ecs.spawn_at(entity, ());
+        for component in components {
+            component.insert(&mut ecs, entity).unwrap();
+        }
+    }
+    Ok(State {
+        ecs,
+        map: data.map,
+        dungeon: data.dungeon,
+        player_entity: data.player_entity,
+        rng: data.rng,
+        seed: data.seed,
+        messages: data.messages,
+        has_moved: false,
+        turn_order: data.turn_order,
+        noises: Vec::new(),
+        shots: Vec::new(),
+        events: Events::default(),
+        stats: data.stats,
+        flow: FlowField::default(),
+        operating_mode: OperatingMode::Ticking,
+        debug: false,
+        recorder: None,
+        playback: None,
+    })
+}
+
+pub fn save_exists() -> bool {
+    std::path::Path::new(SAVE_PATH).exists()
+}
+
+pub fn save_to_file(state: &State) -> io::Result<()> {
+    fs::write(SAVE_PATH, save_game(state))
+}
+
+pub fn load_from_file() -> io::Result<State> {
+    let json = fs::read_to_string(SAVE_PATH)?;
+    Ok(load_game(&json)?)
+}
+
+/// Runs are permadeath, so the save goes away with the player
+pub fn delete_save() {
+    let _ = fs::remove_file(SAVE_PATH);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blueprint::BPImage,
+        components::*,
+        equipment::{build_blueprint, EquipmentType},
+        essence::Essence,
+    };
+
+    #[test]
+    fn save_roundtrip() {
+        let mut rng = RandomNumberGenerator::seeded(7);
+        let map = Map::new(0, &mut rng);
+        let mut ecs = World::new();
+        let sword = build_blueprint(&Blueprint {
+            img: BPImage::Sword,
+            equipment: EquipmentType::Sword,
+            filled: vec![(
+                0,
+                Essence {
+                    element: Elemental::Water,
+                    power: 1,
+                },
+            )],
+        });
+        let item = ecs.spawn((Name("Armor".to_string()), Item {}));
+        let player_entity = ecs.spawn((
+            Health { max_hp: 30, hp: 12 },
+            Position(map.start),
+            Player {
+                current_blueprint: None,
+                passive_equipment: vec![Some(sword)],
+                active_equipment: vec![],
+            },
+            Inventory {
+                contents: vec![item],
+            },
+            Name("Bob".to_string()),
+        ));
+        let mut turn_order = Scheduler::default();
+        turn_order.add(player_entity);
+        let mut state = State {
+            ecs,
+            map,
+            dungeon: Dungeon::default(),
+            player_entity,
+            rng,
+            seed: 7,
+            messages: MessageLog::default(),
+            has_moved: false,
+            turn_order,
+            noises: Vec::new(),
+            shots: Vec::new(),
+            events: Events::default(),
+            stats: Stats {
+                kills: 3,
+                ..Stats::default()
+            },
+            flow: FlowField::default(),
+            operating_mode: OperatingMode::Ticking,
+            debug: false,
+            recorder: None,
+            playback: None,
+        };
+        state.messages.enqueue_message("Hello.");
+
+        let saved = save_game(&state);
+        let mut loaded = load_game(&saved).unwrap();
+        assert_eq!(saved, save_game(&loaded));
+        assert_eq!(state.rng.next_u64(), loaded.rng.next_u64());
+        assert_eq!(loaded.stats.kills, 3);
+
+        let player = loaded.ecs.query_one_mut::<&Player>(player_entity).unwrap();
+        let sword = player.passive_equipment[0].as_ref().unwrap();
+        assert_eq!(sword.ingredients.0, EquipmentType::Sword);
+        assert_eq!(sword.ingredients.1[0].element, Elemental::Water);
+    }
+}
@@ -0,0 +1,145 @@
+//! Energy based turn order. Every round each actor gains energy equal to its speed, and anyone with at least
+//! `ACTION_COST` energy may act, paying for whatever they do. A round is one turn at `NORMAL_SPEED`.
+
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::{Speed, StatusEffects},
+    status::StatusKind,
+};
+
+pub const NORMAL_SPEED: i32 = 100;
+pub const ACTION_COST: i32 = 100;
+pub const MOVE_COST: i32 = 100;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scheduler {
+    /// (actor, energy), in the order ties are broken
+    actors: Vec<(Entity, i32)>,
+}
+
+impl Scheduler {
+    pub fn add(&mut self, entity: Entity) {
+        if !self.contains(entity) {
+            self.actors.push((entity, 0));
+        }
+    }
+    pub fn remove(&mut self, entity: Entity) {
+        self.actors.retain(|(e, _)| *e != entity);
+    }
+    pub fn clear(&mut self) {
+        self.actors.clear();
+    }
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.actors.iter().any(|(e, _)| *e == entity)
+    }
+    pub fn len(&self) -> usize {
+        self.actors.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.actors.is_empty()
+    }
+    pub fn energy(&self, entity: Entity) -> Option<i32> {
+        self.actors
+            .iter()
+            .find(|(e, _)| *e == entity)
+            .map(|(_, energy)| *energy)
+    }
+
+    /// Whoever gets to act now, if anyone. Actors that have been despawned are dropped here, so nothing
+    /// else needs to take them out of the order.
+    pub fn ready(&mut self, ecs: &World) -> Option<Entity> {
+        self.actors.retain(|(e, _)| ecs.contains(*e));
+        self.actors
+            .iter()
+            .find(|(_, energy)| *energy >= ACTION_COST)
+            .map(|(e, _)| *e)
+    }
+
+    /// Hands out a round's worth of energy
+    pub fn new_round(&mut self, ecs: &World) {
+        for (entity, energy) in self.actors.iter_mut() {
+            *energy += speed(ecs, *entity);
+        }
+    }
+
+    pub fn spend(&mut self, entity: Entity, cost: i32) {
+        if let Some((_, energy)) = self.actors.iter_mut().find(|(e, _)| *e == entity) {
+            *energy -= cost;
+        }
+    }
+}
+
+pub fn speed(ecs: &World, entity: Entity) -> i32 {
+    let Ok(mut query) = ecs.query_one::<(Option<&Speed>, Option<&StatusEffects>)>(entity) else {
+        return NORMAL_SPEED;
+    };
+    let Some((speed, statuses)) = query.get() else {
+        return NORMAL_SPEED;
+    };
+    let mut speed = speed.map_or(NORMAL_SPEED, |s| s.0);
+    if let Some(statuses) = statuses {
+        if statuses.has(StatusKind::Freeze) {
+            return 0;
+        }
+        if statuses.has(StatusKind::Slow) {
+            speed /= 2;
+        }
+    }
+    speed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::StatusEffect;
+
+    fn take_turns(sched: &mut Scheduler, ecs: &World, turns: usize) -> Vec<Entity> {
+        let mut acted = vec![];
+        while acted.len() < turns {
+            if let Some(e) = sched.ready(ecs) {
+                acted.push(e);
+                sched.spend(e, ACTION_COST);
+            } else {
+                sched.new_round(ecs);
+            }
+        }
+        acted
+    }
+
+    #[test]
+    fn speeds() {
+        let mut ecs = World::new();
+        let normal = ecs.spawn((Speed(NORMAL_SPEED),));
+        let fast = ecs.spawn((Speed(NORMAL_SPEED * 2),));
+        let slowed = ecs.spawn((StatusEffects(vec![StatusEffect {
+            kind: StatusKind::Slow,
+            duration: 10,
+            power: 0,
+        }]),));
+        let mut sched = Scheduler::default();
+        sched.add(normal);
+        sched.add(fast);
+        sched.add(slowed);
+        let acted = take_turns(&mut sched, &ecs, 14);
+        let count = |e| acted.iter().filter(|a| **a == e).count();
+        assert_eq!(count(normal), 4);
+        assert_eq!(count(fast), 8);
+        assert_eq!(count(slowed), 2);
+    }
+
+    #[test]
+    fn despawned_are_dropped() {
+        let mut ecs = World::new();
+        let a = ecs.spawn((Speed(NORMAL_SPEED),));
+        let b = ecs.spawn((Speed(NORMAL_SPEED),));
+        let mut sched = Scheduler::default();
+        sched.add(a);
+        sched.add(b);
+        sched.new_round(&ecs);
+        ecs.despawn(a).unwrap();
+        assert_eq!(sched.ready(&ecs), Some(b));
+        assert_eq!(sched.len(), 1);
+    }
+}
@@ -1,46 +1,424 @@
-use crate::components::*;
-use crate::State;
+//! What monsters can do besides walking up and hitting you. Which skills a monster has, and how often it tries
+//! each, is its `Skilled` component in the raws; everything about a skill (reach, damage, cooldown) is data in
+//! there too. A skill that can't be used right now returns `None` and the monster does its usual thing instead.
+
+use crate::{
+    combat::{post, Event, HitKind},
+    components::*,
+    damage::Damage,
+    equipment::element_color,
+    map::is_safe_spot,
+    monster::spawn_monster_named,
+    status::{apply_status, has_status, StatusEffect, StatusKind},
+    State,
+};
 use bracket_lib::prelude::*;
 use hecs::Entity;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+fn half() -> f32 {
+    0.5
+}
+
+fn full() -> f32 {
+    1.0
+}
+
+fn one() -> i32 {
+    1
+}
+
+fn bolt() -> char {
+    '*'
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum Skill {
-    ShootBow,
+    /// hits the target from up to `range` away, if nothing's in the way
+    Ranged {
+        range: i32,
+        /// of the monster's `Attack` damage
+        #[serde(default = "half")]
+        multiplier: f32,
+        #[serde(default)]
+        element: Option<Elemental>,
+        /// drawn along the way it went
+        #[serde(default = "bolt")]
+        glyph: char,
+        #[serde(default)]
+        cooldown: u32,
+    },
+    /// puts `effect` on itself, if it doesn't have it already
+    Buff {
+        effect: StatusEffect,
+        #[serde(default)]
+        cooldown: u32,
+    },
+    /// calls up `count` of the monster called `name` under `rank` in `spawns.json` around itself
+    Summon {
+        name: String,
+        rank: i32,
+        #[serde(default = "one")]
+        count: i32,
+        #[serde(default)]
+        cooldown: u32,
+    },
+    /// jumps to somewhere up to `range` away that it can see and that's closer to the target
+    Blink {
+        range: i32,
+        #[serde(default)]
+        cooldown: u32,
+    },
+    /// hits everything within `radius` of the target, which has to be up to `range` away and in the clear
+    Breath {
+        range: i32,
+        radius: i32,
+        /// of the monster's `Attack` damage
+        #[serde(default = "full")]
+        multiplier: f32,
+        #[serde(default)]
+        element: Option<Elemental>,
+        #[serde(default)]
+        cooldown: u32,
+    },
+    /// heals every hurt monster it can see up to `range` away by `amount`, itself included
+    HealAllies {
+        amount: i32,
+        range: i32,
+        #[serde(default)]
+        cooldown: u32,
+    },
+}
+
+/// A projectile or blast that went off since the player's last action, drawn over the map until their next one
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shot {
+    pub tiles: Vec<Point>,
+    pub glyph: char,
+    pub fg: RGB,
+}
+
+impl Skilled {
+    pub fn ready(&self, idx: usize) -> bool {
+        self.cooldowns.get(idx).map_or(true, |rounds| *rounds == 0)
+    }
+
+    pub fn start_cooldown(&mut self, idx: usize) {
+        self.cooldowns.resize(self.skills.len(), 0);
+        self.cooldowns[idx] = self.skills[idx].1.cooldown();
+    }
+
+    /// Counts a round off every cooldown
+    pub fn tick(&mut self) {
+        for rounds in self.cooldowns.iter_mut() {
+            *rounds = rounds.saturating_sub(1);
+        }
+    }
 }
 
 impl Skill {
-    /// Returns true if succeeds. Otherwise, try a different skill
-    pub fn apply(self, source: Entity, target: Entity, state: &mut State) -> Option<()> {
+    /// rounds it takes before it can be used again
+    pub fn cooldown(&self) -> u32 {
+        match self {
+            Skill::Ranged { cooldown, .. }
+            | Skill::Buff { cooldown, .. }
+            | Skill::Summon { cooldown, .. }
+            | Skill::Blink { cooldown, .. }
+            | Skill::Breath { cooldown, .. }
+            | Skill::HealAllies { cooldown, .. } => *cooldown,
+        }
+    }
+
+    /// Returns `Some` if it was used. Otherwise, try a different skill
+    pub fn apply(&self, source: Entity, target: Entity, state: &mut State) -> Option<()> {
         debug_assert!(target != source);
+        let (name, pos, attack) = {
+            let mut query = state
+                .ecs
+                .query_one::<(&Name, &Position, Option<&Attack>)>(source)
+                .ok()?;
+            let (name, pos, attack) = query.get()?;
+            (name.0.clone(), pos.0, attack.map_or(0, |a| a.damage))
+        };
+        let target_pos = state.ecs.query_one_mut::<&Position>(target).ok()?.0;
+        let seen = state.map.visible_tiles[state.map.point2d_to_index(pos)];
         match self {
-            Skill::ShootBow => {
-                let mut mon_query = state
+            Skill::Ranged {
+                range,
+                multiplier,
+                element,
+                glyph,
+                ..
+            } => {
+                let path = clear_line(state, pos, target_pos, *range)?;
+                state.shots.push(Shot {
+                    tiles: path,
+                    glyph: *glyph,
+                    fg: element.as_ref().map_or(RGB::named(WHITE), element_color),
+                });
+                let damage = Damage {
+                    amount: (attack as f32 * multiplier).round() as i32,
+                    element: element.clone(),
+                };
+                post(
+                    state,
+                    Event::Hit {
+                        source: Some(source),
+                        target,
+                        damage,
+                        kind: HitKind::Shot,
+                    },
+                );
+                if let Some(element) = element {
+                    element_rider(state, pos, target, element);
+                }
+                Some(())
+            }
+            Skill::Buff { effect, .. } => {
+                if has_status(&state.ecs, source, effect.kind) {
+                    return None;
+                }
+                apply_status(&mut state.ecs, source, effect.clone());
+                if seen {
+                    state.messages.enqueue_message(&format!(
+                        "The {} is {}.",
+                        name,
+                        effect.kind.name().to_lowercase(),
+                    ));
+                }
+                Some(())
+            }
+            Skill::Summon {
+                name: summoned,
+                rank,
+                count,
+                ..
+            } => {
+                let spots = free_spots_around(state, pos, target_pos, *count as usize);
+                if spots.is_empty() {
+                    return None;
+                }
+                for spot in spots {
+                    let entity = spawn_monster_named(state, *rank, summoned, spot);
+                    if let Ok(mon) = state.ecs.query_one_mut::<&mut Monster>(entity) {
+                        mon.awareness = Awareness::Searching;
+                        mon.tracking = Some(target_pos);
+                        mon.memory = crate::perception::MEMORY;
+                    }
+                    state.turn_order.add(entity);
+                }
+                if seen {
+                    state
+                        .messages
+                        .enqueue_message(&format!("The {} calls for help!", name));
+                }
+                Some(())
+            }
+            Skill::Blink { range, .. } => {
+                let visible = state
                     .ecs
-                    .query_one::<(&Name, &Position, &Attack)>(source)
-                    .unwrap();
-                let (mon_name, mon_pos, mon_attack) = mon_query.get()?;
-                let mut player_query = state
+                    .query_one_mut::<&Viewer>(source)
+                    .ok()?
+                    .visible_tiles
+                    .clone();
+                let now = DistanceAlg::Pythagoras.distance2d(pos, target_pos);
+                let spots: Vec<Point> = visible
+                    .into_iter()
+                    .filter(|pt| {
+                        DistanceAlg::Chebyshev.distance2d(pos, *pt) <= *range as f32
+                            && DistanceAlg::Pythagoras.distance2d(*pt, target_pos) < now
+                            && *pt != target_pos
+                            && is_free(state, *pt)
+                    })
+                    .collect();
+                if spots.is_empty() {
+                    return None;
+                }
+                let dest = spots[state.rng.range(0, spots.len())];
+                let (position, viewer) = state
                     .ecs
-                    .query_one::<(&Position, &mut Health)>(target)
-                    .unwrap();
-                let (player_pos, player_health) = player_query.get()?;
-
-                let line = Bresenham::new(mon_pos.0, player_pos.0);
-                for step in line.skip(1) {
-                    let idx = state.map.point2d_to_index(step);
-                    if !state.map.is_available_exit(idx) {
-                        return None;
+                    .query_one_mut::<(&mut Position, &mut Viewer)>(source)
+                    .ok()?;
+                position.0 = dest;
+                viewer.dirty = true;
+                if seen || state.map.visible_tiles[state.map.point2d_to_index(dest)] {
+                    state
+                        .messages
+                        .enqueue_message(&format!("The {} blinks closer!", name));
+                }
+                Some(())
+            }
+            Skill::Breath {
+                range,
+                radius,
+                multiplier,
+                element,
+                ..
+            } => {
+                clear_line(state, pos, target_pos, *range)?;
+                let damage = Damage {
+                    amount: (attack as f32 * multiplier).round() as i32,
+                    element: element.clone(),
+                };
+                let area: Vec<Point> = state
+                    .ecs
+                    .query_one_mut::<&Viewer>(source)
+                    .map(|v| v.visible_tiles.clone())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|pt| {
+                        DistanceAlg::Pythagoras.distance2d(*pt, target_pos) <= *radius as f32
+                    })
+                    .collect();
+                let mut caught = vec![];
+                for (entity, (p, _)) in state.ecs.query_mut::<(&Position, &Health)>() {
+                    if entity != source && area.contains(&p.0) {
+                        caught.push(entity);
                     }
                 }
-                let damage = (mon_attack.damage as f32 * 0.5).round() as i32;
-                player_health.hp -= damage;
-                state.messages.enqueue_message(&format!(
-                    "The {} shoots you for {} damage.",
-                    mon_name.0, damage,
-                ));
-                return Some(());
+                for entity in caught {
+                    post(
+                        state,
+                        Event::Hit {
+                            source: Some(source),
+                            target: entity,
+                            damage: damage.clone(),
+                            kind: HitKind::Breath,
+                        },
+                    );
+                    if let Some(element) = element {
+                        element_rider(state, target_pos, entity, element);
+                    }
+                }
+                state.shots.push(Shot {
+                    tiles: area,
+                    glyph: '*',
+                    fg: element.as_ref().map_or(RGB::named(WHITE), element_color),
+                });
+                Some(())
             }
+            Skill::HealAllies { amount, range, .. } => {
+                let visible = state
+                    .ecs
+                    .query_one_mut::<&Viewer>(source)
+                    .map(|v| v.visible_tiles.clone())
+                    .unwrap_or_default();
+                let mut healed = 0;
+                for (_, (p, health, _)) in
+                    state.ecs.query_mut::<(&Position, &mut Health, &Monster)>()
+                {
+                    let near = p.0 == pos
+                        || (visible.contains(&p.0)
+                            && DistanceAlg::Pythagoras.distance2d(pos, p.0) <= *range as f32);
+                    if near && health.hp < health.max_hp {
+                        health.hp = (health.hp + amount).min(health.max_hp);
+                        healed += 1;
+                    }
+                }
+                if healed == 0 {
+                    return None;
+                }
+                if seen {
+                    state
+                        .messages
+                        .enqueue_message(&format!("The {} mends its allies.", name));
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+/// The tiles between `from` and `to`, if `to` is in reach and nothing stands in the way
+fn clear_line(state: &State, from: Point, to: Point, range: i32) -> Option<Vec<Point>> {
+    if DistanceAlg::Pythagoras.distance2d(from, to) > range as f32 {
+        return None;
+    }
+    let mut path = vec![];
+    for step in Bresenham::new(from, to).skip(1) {
+        let idx = state.map.point2d_to_index(step);
+        if !state.map.is_available_exit(idx) {
+            return None;
         }
+        path.push(step);
+    }
+    Some(path)
+}
+
+fn is_free(state: &State, pt: Point) -> bool {
+    let idx = state.map.point2d_to_index(pt);
+    state.map.in_bounds(pt)
+        && is_safe_spot(state.map.tiles[idx])
+        && !state.map.blocked_tiles[idx]
+        && state.map.tile_contents[idx].is_empty()
+}
+
+/// Up to `count` free tiles next to `center`, never where the player is
+fn free_spots_around(state: &State, center: Point, avoid: Point, count: usize) -> Vec<Point> {
+    let mut spots = vec![];
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let pt = center + Point::new(dx, dy);
+            if spots.len() < count && pt != center && pt != avoid && is_free(state, pt) {
+                spots.push(pt);
+            }
+        }
+    }
+    spots
+}
+
+/// What being hit with an element does on top of the damage: fire burns, water slows and air knocks back
+fn element_rider(state: &mut State, from: Point, target: Entity, element: &Elemental) {
+    match element {
+        Elemental::Fire => apply_status(
+            &mut state.ecs,
+            target,
+            StatusEffect {
+                kind: StatusKind::Burn,
+                duration: 2,
+                power: 1,
+            },
+        ),
+        Elemental::Water => apply_status(
+            &mut state.ecs,
+            target,
+            StatusEffect {
+                kind: StatusKind::Slow,
+                duration: 2,
+                power: 0,
+            },
+        ),
+        Elemental::Air => {
+            let Ok(pos) = state.ecs.query_one_mut::<&Position>(target).map(|p| p.0) else { return };
+            if pos != from && state.ecs.satisfies::<&Viewer>(target).unwrap_or(false) {
+                let dest = pos + crate::math::normalize_pt(pos - from);
+                post(state, Event::Push { entity: target, dest });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldowns_count_down() {
+        let mut skilled = Skilled {
+            skills: vec![
+                (1.0, Skill::Blink { range: 3, cooldown: 2 }),
+                (1.0, Skill::Blink { range: 3, cooldown: 0 }),
+            ],
+            cooldowns: vec![],
+        };
+        assert!(skilled.ready(0) && skilled.ready(1));
+        skilled.start_cooldown(0);
+        skilled.start_cooldown(1);
+        assert!(!skilled.ready(0));
+        assert!(skilled.ready(1));
+        skilled.tick();
+        assert!(!skilled.ready(0));
+        skilled.tick();
+        assert!(skilled.ready(0));
     }
 }
@@ -0,0 +1,152 @@
+//! Which monsters and items turn up on which depths, and how many of them, from the `table` in `raws/spawns.json`.
+//! Monsters and items are picked the same way: by weight, from the entries whose depth range covers the floor, a
+//! group at a time.
+
+use std::collections::HashMap;
+
+use bracket_lib::prelude::*;
+use rand::{distributions::WeightedIndex, prelude::Distribution};
+use serde::Deserialize;
+
+use crate::{components::Name, components_serde::Component};
+
+fn default_weight() -> i32 {
+    1
+}
+
+fn single() -> (usize, usize) {
+    (1, 1)
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct SpawnEntry {
+    /// the `Name` of a monster or item listed under `rank`
+    pub name: String,
+    /// which list in `spawns.json` it's from, which is also how much its essence is worth
+    pub rank: i32,
+    #[serde(default = "default_weight")]
+    pub weight: i32,
+    /// the shallowest and deepest depths it turns up on
+    pub depths: (i32, i32),
+    /// how few and how many turn up together
+    #[serde(default = "single")]
+    pub group: (usize, usize),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SpawnCounts {
+    pub monsters: usize,
+    pub items: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SpawnTable {
+    pub monsters: Vec<SpawnEntry>,
+    pub items: Vec<SpawnEntry>,
+    /// how many of each a depth gets, none if it isn't listed
+    pub counts: HashMap<i32, SpawnCounts>,
+}
+
+impl SpawnTable {
+    pub fn counts(&self, depth: i32) -> SpawnCounts {
+        self.counts.get(&depth).cloned().unwrap_or_default()
+    }
+}
+
+impl SpawnEntry {
+    pub fn allowed_on(&self, depth: i32) -> bool {
+        (self.depths.0..=self.depths.1).contains(&depth)
+    }
+
+    pub fn group_size(&self, rng: &mut RandomNumberGenerator) -> usize {
+        rng.range(self.group.0, self.group.1 + 1)
+    }
+}
+
+/// Picks one of the entries allowed on `depth` by weight, with `bonus` multiplying the weight of each
+pub fn pick<'a>(
+    entries: &'a [SpawnEntry],
+    depth: i32,
+    bonus: impl Fn(&SpawnEntry) -> i32,
+    rng: &mut RandomNumberGenerator,
+) -> Option<&'a SpawnEntry> {
+    let allowed: Vec<&SpawnEntry> = entries.iter().filter(|e| e.allowed_on(depth)).collect();
+    let dist = WeightedIndex::new(allowed.iter().map(|e| e.weight * bonus(e))).ok()?;
+    Some(allowed[dist.sample(rng.get_rng())])
+}
+
+/// The components of whatever is called `name` in the `rank` list of `defs`
+pub fn lookup<'a>(
+    defs: &'a HashMap<i32, Vec<Vec<Component>>>,
+    rank: i32,
+    name: &str,
+) -> Option<&'a Vec<Component>> {
+    defs.get(&rank)?.iter().find(|components| {
+        components
+            .iter()
+            .any(|c| matches!(c, Component::Name(Name(n)) if n == name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raws::Raws;
+
+    fn raws() -> Raws {
+        serde_json::from_str(include_str!("../raws/spawns.json")).unwrap()
+    }
+
+    #[test]
+    fn every_entry_exists() {
+        let raws = raws();
+        let table = &raws.table;
+        for entry in table.monsters.iter() {
+            assert!(
+                lookup(&raws.monsters, entry.rank, &entry.name).is_some(),
+                "{}",
+                entry.name
+            );
+        }
+        for entry in table.items.iter() {
+            assert!(
+                lookup(&raws.items, entry.rank, &entry.name).is_some(),
+                "{}",
+                entry.name
+            );
+        }
+        for entry in table.monsters.iter().chain(table.items.iter()) {
+            assert!(
+                entry.group.0 >= 1 && entry.group.0 <= entry.group.1,
+                "{}",
+                entry.name
+            );
+            assert!(entry.depths.0 <= entry.depths.1, "{}", entry.name);
+        }
+    }
+
+    #[test]
+    fn every_populated_depth_has_something() {
+        let raws = raws();
+        let mut rng = RandomNumberGenerator::seeded(3);
+        for (depth, counts) in raws.table.counts.iter() {
+            if counts.monsters > 0 {
+                assert!(pick(&raws.table.monsters, *depth, |_| 1, &mut rng).is_some());
+            }
+            if counts.items > 0 {
+                assert!(pick(&raws.table.items, *depth, |_| 1, &mut rng).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn picks_stay_in_range() {
+        let raws = raws();
+        let mut rng = RandomNumberGenerator::seeded(8);
+        for _ in 0..100 {
+            let entry = pick(&raws.table.monsters, 0, |_| 1, &mut rng).unwrap();
+            assert!(entry.allowed_on(0));
+        }
+        assert!(pick(&raws.table.monsters, 100, |_| 1, &mut rng).is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use bracket_lib::prelude::*;
+use hecs::{Entity, World};
+use serde::{Deserialize, Serialize};
+
+use crate::components::StatusEffects;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    /// gains energy at half speed
+    Slow,
+    /// takes `power` damage every round
+    Burn,
+    /// gains no energy at all
+    Freeze,
+    /// loses every turn it would get
+    Stun,
+    /// can't be pushed or pulled
+    KnockbackImmune,
+    /// heals `power` every round
+    Regen,
+}
+
+enum Stacking {
+    /// durations add up
+    Extend,
+    /// the longer duration wins
+    Refresh,
+    /// powers add up, the longer duration wins
+    Intensify,
+}
+
+impl StatusKind {
+    fn stacking(self) -> Stacking {
+        match self {
+            StatusKind::Slow => Stacking::Extend,
+            StatusKind::Burn => Stacking::Intensify,
+            StatusKind::Freeze => Stacking::Refresh,
+            StatusKind::Stun => Stacking::Refresh,
+            StatusKind::KnockbackImmune => Stacking::Refresh,
+            StatusKind::Regen => Stacking::Extend,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Slow => "Slowed",
+            StatusKind::Burn => "Burning",
+            StatusKind::Freeze => "Frozen",
+            StatusKind::Stun => "Stunned",
+            StatusKind::KnockbackImmune => "Anchored",
+            StatusKind::Regen => "Regenerating",
+        }
+    }
+    /// Background drawn behind an affected entity
+    pub fn color(self) -> RGB {
+        match self {
+            StatusKind::Slow => RGB::named(DARKBLUE),
+            StatusKind::Burn => RGB::named(DARKRED),
+            StatusKind::Freeze => RGB::named(STEELBLUE),
+            StatusKind::Stun => RGB::named(DARKGOLDENROD),
+            StatusKind::KnockbackImmune => RGB::named(DIMGRAY),
+            StatusKind::Regen => RGB::named(DARKGREEN),
+        }
+    }
+    /// (said of the player, said of anything else)
+    pub fn expiry_message(self) -> (&'static str, &'static str) {
+        match self {
+            StatusKind::Slow => ("You speed back up.", "speeds back up."),
+            StatusKind::Burn => ("You stop burning.", "stops burning."),
+            StatusKind::Freeze => ("You thaw out.", "thaws out."),
+            StatusKind::Stun => ("You shake off the stun.", "shakes off the stun."),
+            StatusKind::KnockbackImmune => ("You feel less steady.", "looks less steady."),
+            StatusKind::Regen => ("You stop regenerating.", "stops regenerating."),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    /// rounds left
+    pub duration: u32,
+    #[serde(default)]
+    pub power: i32,
+}
+
+impl StatusEffects {
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.0.iter().find(|s| s.kind == kind)
+    }
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+    pub fn add(&mut self, effect: StatusEffect) {
+        let Some(existing) = self.0.iter_mut().find(|s| s.kind == effect.kind) else {
+            self.0.push(effect);
+            return;
+        };
+        match effect.kind.stacking() {
+            Stacking::Extend => {
+                existing.duration += effect.duration;
+                existing.power = existing.power.max(effect.power);
+            }
+            Stacking::Refresh => {
+                existing.duration = existing.duration.max(effect.duration);
+                existing.power = existing.power.max(effect.power);
+            }
+            Stacking::Intensify => {
+                existing.duration = existing.duration.max(effect.duration);
+                existing.power += effect.power;
+            }
+        }
+    }
+    /// Counts a round off every effect, returning the ones that ran out
+    pub fn tick(&mut self) -> Vec<StatusKind> {
+        for effect in self.0.iter_mut() {
+            effect.duration = effect.duration.saturating_sub(1);
+        }
+        let expired = self
+            .0
+            .iter()
+            .filter(|s| s.duration == 0)
+            .map(|s| s.kind)
+            .collect();
+        self.0.retain(|s| s.duration > 0);
+        expired
+    }
+}
+
+/// Puts an effect on anything, stacking it with what's already there
+pub fn apply_status(ecs: &mut World, entity: Entity, effect: StatusEffect) {
+    if let Ok(statuses) = ecs.query_one_mut::<&mut StatusEffects>(entity) {
+        statuses.add(effect);
+        return;
+    }
+    let _ = ecs.insert_one(entity, StatusEffects(vec![effect]));
+}
+
+/// Tops an effect up to at least `effect`'s duration and power without stacking it, for effects that should last
+/// as long as whatever is causing them. Returns whether it's new.
+pub fn keep_status(ecs: &mut World, entity: Entity, effect: StatusEffect) -> bool {
+    if let Ok(statuses) = ecs.query_one_mut::<&mut StatusEffects>(entity) {
+        if let Some(existing) = statuses.0.iter_mut().find(|s| s.kind == effect.kind) {
+            existing.duration = existing.duration.max(effect.duration);
+            existing.power = existing.power.max(effect.power);
+            return false;
+        }
+    }
+    apply_status(ecs, entity, effect);
+    true
+}
+
+pub fn has_status(ecs: &World, entity: Entity, kind: StatusKind) -> bool {
+    ecs.get::<&StatusEffects>(entity)
+        .map_or(false, |s| s.has(kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(kind: StatusKind, duration: u32, power: i32) -> StatusEffect {
+        StatusEffect {
+            kind,
+            duration,
+            power,
+        }
+    }
+
+    #[test]
+    fn stacking() {
+        let mut s = StatusEffects::default();
+        s.add(effect(StatusKind::Slow, 2, 0));
+        s.add(effect(StatusKind::Slow, 3, 0));
+        s.add(effect(StatusKind::Burn, 2, 1));
+        s.add(effect(StatusKind::Burn, 1, 2));
+        s.add(effect(StatusKind::Stun, 1, 0));
+        s.add(effect(StatusKind::Stun, 3, 0));
+        assert_eq!(s.get(StatusKind::Slow).unwrap().duration, 5);
+        assert_eq!(s.get(StatusKind::Burn).unwrap().duration, 2);
+        assert_eq!(s.get(StatusKind::Burn).unwrap().power, 3);
+        assert_eq!(s.get(StatusKind::Stun).unwrap().duration, 3);
+    }
+
+    #[test]
+    fn keeping_doesnt_stack() {
+        let mut ecs = World::new();
+        let e = ecs.spawn(());
+        assert!(keep_status(&mut ecs, e, effect(StatusKind::Burn, 2, 2)));
+        assert!(!keep_status(&mut ecs, e, effect(StatusKind::Burn, 2, 2)));
+        let statuses = ecs.get::<&StatusEffects>(e).unwrap();
+        assert_eq!(statuses.get(StatusKind::Burn).unwrap().power, 2);
+        assert_eq!(statuses.get(StatusKind::Burn).unwrap().duration, 2);
+    }
+
+    #[test]
+    fn expiry() {
+        let mut s = StatusEffects::default();
+        s.add(effect(StatusKind::Freeze, 1, 0));
+        s.add(effect(StatusKind::Regen, 2, 1));
+        assert_eq!(s.tick(), vec![StatusKind::Freeze]);
+        assert!(s.has(StatusKind::Regen));
+        assert_eq!(s.tick(), vec![StatusKind::Regen]);
+        assert!(s.0.is_empty());
+    }
+}
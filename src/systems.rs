@@ -1,4 +1,6 @@
 pub mod blockers;
 pub mod death;
 pub mod tile_contents;
+pub mod terrain;
+pub mod timers;
 pub mod viewer_look;
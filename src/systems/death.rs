@@ -1,43 +1,22 @@
+use hecs::Entity;
+
 use crate::{
+    combat::{post, Event},
     components::*,
-    essence::{gain_essence, Essence},
-    OperatingMode, State,
+    State,
 };
 
+/// Anything out of health dies, see `combat` for what that sets off
 pub fn system_kill_dead(state: &mut State) {
-    let mut dead = vec![];
-    let mut reaped_essence = vec![];
-    for (id, (elem, rank, health)) in state
+    let dead: Vec<Entity> = state
         .ecs
-        .query_mut::<(Option<&Elemental>, Option<&Rank>, &Health)>()
-    {
-        if health.hp <= 0 {
-            dead.push(id);
-            if let (Some(elem), Some(rank)) = (elem, rank) {
-                if state.rng.range(0, 3) == 0 {
-                    // 1 in 3
-                    reaped_essence.push(Essence {
-                        element: elem.clone(),
-                        power: rank.clone().0,
-                    });
-                }
-            }
-        }
-    }
-    for id in dead {
-        if id == state.player_entity {
-            state.messages.enqueue_message("You are DEAD.");
-            state.operating_mode = OperatingMode::GameOver;
-            continue;
-        }
-        state.ecs.despawn(id).unwrap();
-        if let Some((i, _)) = state.turn_order.iter().enumerate().find(|(_, e)| **e == id) {
-            state.turn_order.remove(i);
-        }
-    }
-    for essence in reaped_essence {
-        if essence.power <= 2 && essence.power >= 0 {
-            gain_essence(state, essence);
-        }
+        .query_mut::<&Health>()
+        .into_iter()
+        .filter(|(_, health)| health.hp <= 0)
+        .map(|(id, _)| id)
+        .collect();
+    for entity in dead {
+        let killer = state.events.killer(entity);
+        post(state, Event::Death { entity, killer });
     }
 }
@@ -0,0 +1,76 @@
+use bracket_lib::prelude::*;
+use hecs::{Entity, With};
+
+use crate::{
+    combat::{post, Event},
+    components::*,
+    map::Tile,
+    status::{keep_status, StatusEffect, StatusKind},
+    State,
+};
+
+/// how far wind tunnels blow things each round
+const WIND_PUSH: i32 = 3;
+
+/// What the ground does to whoever is standing on it, once a round. Anything that does damage sets you burning,
+/// and nothing is bothered by terrain of its own element.
+pub fn system_terrain(state: &mut State) {
+    let standing: Vec<(Entity, Point)> = state
+        .ecs
+        .query_mut::<With<&Position, &Health>>()
+        .into_iter()
+        .map(|(e, pos)| (e, pos.0))
+        .collect();
+    for (entity, pos) in standing {
+        let tile = state.map.tiles[state.map.point2d_to_index(pos)];
+        let info = tile.info();
+        if let (Some(element), Ok(own)) = (&info.element, state.ecs.get::<&Elemental>(entity)) {
+            if *own == *element {
+                continue;
+            }
+        }
+        let is_player = entity == state.player_entity;
+        if info.damage > 0 {
+            let effect = StatusEffect {
+                kind: StatusKind::Burn,
+                duration: 2,
+                power: info.damage,
+            };
+            if keep_status(&mut state.ecs, entity, effect) && is_player {
+                state.messages.enqueue_message(&format!(
+                    "The {} sets you alight!",
+                    info.name.to_lowercase()
+                ));
+            }
+        }
+        match tile {
+            Tile::DeepWater => {
+                let effect = StatusEffect {
+                    kind: StatusKind::Slow,
+                    duration: 2,
+                    power: 0,
+                };
+                if keep_status(&mut state.ecs, entity, effect) && is_player {
+                    state.messages.enqueue_message("You wade into deep water.");
+                }
+            }
+            Tile::Wind(dir) => {
+                // pushing needs something that can see where it ends up
+                if !state.ecs.satisfies::<&Viewer>(entity).unwrap_or(false) {
+                    continue;
+                }
+                let dest = pos + dir.delta() * WIND_PUSH;
+                let dest = Point::new(
+                    dest.x.clamp(0, state.map.width - 1),
+                    dest.y.clamp(0, state.map.height - 1),
+                );
+                post(state, Event::Push { entity, dest });
+                let moved = state.ecs.get::<&Position>(entity).map_or(false, |p| p.0 != pos);
+                if moved && is_player {
+                    state.messages.enqueue_message("The wind blows you along.");
+                }
+            }
+            _ => {}
+        }
+    }
+}
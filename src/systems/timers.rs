@@ -0,0 +1,84 @@
+use bracket_lib::prelude::*;
+
+use crate::{
+    combat::{post, Event, HitKind},
+    components::*,
+    damage::Damage,
+    status::StatusKind,
+    State,
+};
+
+/// Counts down everything that lasts a number of rounds
+pub fn system_tick_timers(state: &mut State) {
+    let mut expired_walls = vec![];
+    for (id, wall) in state.ecs.query_mut::<&mut TempWall>() {
+        wall.duration -= 1;
+        if wall.duration <= 0 {
+            expired_walls.push(id);
+        }
+    }
+    if !expired_walls.is_empty() {
+        state.map.changes += 1;
+    }
+    for id in expired_walls {
+        state.ecs.despawn(id).unwrap();
+    }
+
+    for (_, skilled) in state.ecs.query_mut::<&mut Skilled>() {
+        skilled.tick();
+    }
+    for (_, boss) in state.ecs.query_mut::<&mut Boss>() {
+        boss.recharge = boss.recharge.saturating_sub(1);
+    }
+
+    let mut messages = vec![];
+    let mut cleared = vec![];
+    let mut burns = vec![];
+    for (id, (statuses, health, name, pos)) in state.ecs.query_mut::<(
+        &mut StatusEffects,
+        Option<&mut Health>,
+        Option<&Name>,
+        Option<&Position>,
+    )>() {
+        if let Some(health) = health {
+            if let Some(burn) = statuses.get(StatusKind::Burn) {
+                burns.push((id, burn.power));
+            }
+            if let Some(regen) = statuses.get(StatusKind::Regen) {
+                health.hp = (health.hp + regen.power).min(health.max_hp);
+            }
+        }
+        let expired = statuses.tick();
+        if statuses.0.is_empty() {
+            cleared.push(id);
+        }
+        let seen = pos.map_or(false, |p| {
+            state.map.visible_tiles[state.map.point2d_to_index(p.0)]
+        });
+        for kind in expired {
+            let (to_player, to_other) = kind.expiry_message();
+            if id == state.player_entity {
+                messages.push(to_player.to_string());
+            } else if let (Some(name), true) = (name, seen) {
+                messages.push(format!("The {} {}", name.0, to_other));
+            }
+        }
+    }
+    for id in cleared {
+        state.ecs.remove_one::<StatusEffects>(id).unwrap();
+    }
+    for (target, power) in burns {
+        post(
+            state,
+            Event::Hit {
+                source: None,
+                target,
+                damage: Damage::of(power, Elemental::Fire),
+                kind: HitKind::Burn,
+            },
+        );
+    }
+    for message in messages {
+        state.messages.enqueue_message(&message);
+    }
+}
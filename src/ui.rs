@@ -1,10 +1,20 @@
 use crate::{
-    components::*, debug, equipment::print_desc, map, mapping::Command, State, WINDOW_HEIGHT,
-    WINDOW_WIDTH,
+    components::*,
+    damage::defence_of,
+    debug,
+    equipment::{element_color, print_desc},
+    map,
+    mapping::Command,
+    raws::RAWS,
+    State, MAX_SEED_DIGITS, WINDOW_HEIGHT, WINDOW_WIDTH,
 };
 use bracket_lib::prelude::*;
 
 pub const SIDEBAR_EXTRA_POS: Point = Point { x: 1, y: 30 };
+/// most lines an equipment description gets under the blueprint being filled, leaving two for forging
+const BLUEPRINT_DESC_HEIGHT: i32 = WINDOW_HEIGHT - 31 - 2;
+/// most lines the description of equipment being examined gets, down to the bottom of the window
+const EXAMINE_DESC_HEIGHT: i32 = WINDOW_HEIGHT - SIDEBAR_EXTRA_POS.y - 30;
 pub const LEFT_SIDEBAR_WIDTH: i32 = 20;
 pub const RIGHT_SIDEBAR_WIDTH: i32 = 20;
 pub const MESSAGE_LOG_HEIGHT: i32 = 8;
@@ -55,12 +65,20 @@ pub fn draw_messages(state: &State, ctx: &mut BTerm) {
 pub fn draw_side_info(state: &State, ctx: &mut BTerm) {
     let mut query = state
         .ecs
-        .query_one::<(&Health, &Player)>(state.player_entity)
+        .query_one::<(&Health, &Player, Option<&StatusEffects>)>(state.player_entity)
         .unwrap();
-    let (health, player) = query.get().unwrap();
+    let (health, player, statuses) = query.get().unwrap();
 
     ctx.print(1, 1, format!("Depth: {}", state.map.depth + 1));
     ctx.print(1, 2, format!("Health: {}/{}", health.hp, health.max_hp));
+    ctx.print(1, WINDOW_HEIGHT - 2, format!("Seed: {}", state.seed));
+    if let Some(playback) = &state.playback {
+        if playback.speed == 0 {
+            ctx.print(1, WINDOW_HEIGHT - 3, "Replay: paused");
+        } else {
+            ctx.print(1, WINDOW_HEIGHT - 3, format!("Replay: speed {}", playback.speed));
+        }
+    }
 
     let bar_width = LEFT_SIDEBAR_WIDTH - 3;
 
@@ -74,7 +92,12 @@ pub fn draw_side_info(state: &State, ctx: &mut BTerm) {
         RGB::named(GRAY),
     );
 
-    let mut line = 5;
+    let defence = defence_of(&state.ecs, state.player_entity);
+    ctx.print(1, 5, format!("Armor: {}", defence.armor));
+    ctx.print(1, 6, format!("Block: {}%", defence.block));
+    ctx.print(1, 7, format!("Evade: {}%", defence.evasion));
+
+    let mut line = 9;
     ctx.print(1, line, "Actives:");
     line += 1;
     for (i, eq) in player.active_equipment.iter().enumerate() {
@@ -90,6 +113,21 @@ pub fn draw_side_info(state: &State, ctx: &mut BTerm) {
         ctx.print(1, line, format!("{:?}", eq.ingredients.0));
         line += 1;
     }
+    if let Some(statuses) = statuses {
+        line += 1;
+        ctx.print(1, line, "Status:");
+        line += 1;
+        for status in statuses.0.iter() {
+            ctx.print_color(
+                1,
+                line,
+                RGB::named(WHITE),
+                status.kind.color(),
+                format!("{} ({})", status.kind.name(), status.duration),
+            );
+            line += 1;
+        }
+    }
 
     for y in 0..WINDOW_HEIGHT {
         ctx.set(
@@ -102,6 +140,32 @@ pub fn draw_side_info(state: &State, ctx: &mut BTerm) {
     }
 }
 
+/// A health bar along the top of the map for each boss the player can see
+pub fn draw_boss_bars(state: &State, ctx: &mut BTerm) {
+    let x = map::MAP_UI_DIM.x1 + 1;
+    let width = map::MAP_UI_DIM.width() - 2;
+    let mut query = state.ecs.query::<(&Boss, &Health, &Name, &Position)>();
+    let mut bosses: Vec<_> = query
+        .iter()
+        .filter(|(_, (.., pos))| state.map.visible_tiles[state.map.point2d_to_index(pos.0)])
+        .map(|(_, (boss, health, name, _))| (boss, health, name))
+        .collect();
+    bosses.sort_by_key(|(_, _, name)| name.0.clone());
+    for (i, (boss, health, name)) in bosses.into_iter().enumerate() {
+        let y = i as i32 * 2;
+        ctx.print(x, y, format!("{} ({}/{})", name.0, health.hp.max(0), health.max_hp));
+        ctx.draw_bar_horizontal(
+            x,
+            y + 1,
+            width,
+            health.hp.max(0),
+            health.max_hp,
+            element_color(&boss.attack.element()),
+            RGB::named(GRAY),
+        );
+    }
+}
+
 pub fn draw_current_blueprint(state: &State, ctx: &mut BTerm) {
     // images are 17x30
     let sidebar_x = WINDOW_WIDTH - RIGHT_SIDEBAR_WIDTH;
@@ -147,25 +211,49 @@ pub fn draw_current_blueprint(state: &State, ctx: &mut BTerm) {
             }
             let mut builder = TextBuilder::empty();
             print_desc(bp.equipment, &ess, &mut builder);
-            let mut block =
-                TextBlock::new(sidebar_x + 1, offset_y + 30, RIGHT_SIDEBAR_WIDTH - 1, 5);
-            block
-                .print(&builder)
-                .expect("Description text was too long");
-            let mut draw_batch = DrawBatch::new();
-            block.render_to_draw_batch(&mut draw_batch);
-            draw_batch.submit(0).unwrap();
-            render_draw_buffer(ctx).unwrap();
-        }
-        if bp.filled.len() == bpi.gem_spots.len() {
-            ctx.print(sidebar_x + 1, offset_y + 30 + 5, "Artifact ready!");
-            ctx.print(sidebar_x + 1, offset_y + 30 + 5 + 1, "Press 'a' to forge!");
+            let height = draw_desc(
+                ctx,
+                &builder,
+                Point::new(sidebar_x + 1, offset_y + 30),
+                RIGHT_SIDEBAR_WIDTH - 1,
+                BLUEPRINT_DESC_HEIGHT,
+            );
+            if bp.filled.len() == bpi.gem_spots.len() {
+                let y = offset_y + 30 + height;
+                ctx.print(sidebar_x + 1, y, "Artifact ready!");
+                ctx.print(sidebar_x + 1, y + 1, "Press 'a' to forge!");
+            }
         }
     } else {
         ctx.print(sidebar_x + 1, 1, "No active artifact");
     }
 }
 
+/// How many lines `builder` wraps to at `width`, if that's no more than `max_height`
+fn wrapped_height(builder: &TextBuilder, width: i32, max_height: i32) -> Option<i32> {
+    (1..=max_height).find(|h| TextBlock::new(0, 0, width, *h).print(builder).is_ok())
+}
+
+/// Prints an equipment description as tall as it needs to be, returning how tall that was. Past `max_height` it
+/// gets cut off.
+fn draw_desc(
+    ctx: &mut BTerm,
+    builder: &TextBuilder,
+    at: Point,
+    width: i32,
+    max_height: i32,
+) -> i32 {
+    let height = wrapped_height(builder, width, max_height).unwrap_or(max_height);
+    let mut block = TextBlock::new(at.x, at.y, width, height);
+    // whatever fits is still worth showing
+    let _ = block.print(builder);
+    let mut draw_batch = DrawBatch::new();
+    block.render_to_draw_batch(&mut draw_batch);
+    draw_batch.submit(0).unwrap();
+    render_draw_buffer(ctx).unwrap();
+    height
+}
+
 pub fn update_message_log(command: Command) -> bool {
     match command {
         Command::Back => {
@@ -200,7 +288,7 @@ pub fn update_game_over(command: Command) -> bool {
     }
 }
 
-pub fn draw_game_over(ctx: &mut BTerm) {
+pub fn draw_game_over(state: &State, ctx: &mut BTerm) {
     let x = 4;
     let y = 4;
     let w = 92;
@@ -208,9 +296,10 @@ pub fn draw_game_over(ctx: &mut BTerm) {
     ctx.draw_box(x, y, w, h, RGB::named(WHITE), RGB::named(BLACK));
     let xpfile = XpFile::from_resource("../assets/game-over.xp").unwrap();
     ctx.render_xp_sprite(&xpfile, x + 1, y + 1);
+    draw_stats(state, x + 2, y + h - 2, ctx);
 }
 
-pub fn draw_game_won(ctx: &mut BTerm) {
+pub fn draw_game_won(state: &State, ctx: &mut BTerm) {
     let x = 4;
     let y = 4;
     let w = 92;
@@ -218,6 +307,21 @@ pub fn draw_game_won(ctx: &mut BTerm) {
     ctx.draw_box(x, y, w, h, RGB::named(WHITE), RGB::named(BLACK));
     let xpfile = XpFile::from_resource("../assets/you-won.xp").unwrap();
     ctx.render_xp_sprite(&xpfile, x + 1, y + 1);
+    draw_stats(state, x + 2, y + h - 2, ctx);
+}
+
+fn draw_stats(state: &State, x: i32, y: i32, ctx: &mut BTerm) {
+    let stats = &state.stats;
+    ctx.print_color(
+        x,
+        y,
+        RGB::named(WHITE),
+        RGB::named(BLACK),
+        format!(
+            "Kills: {}   Damage dealt: {}   Damage taken: {}   Essence found: {}",
+            stats.kills, stats.damage_dealt, stats.damage_taken, stats.essence
+        ),
+    );
 }
 
 pub fn update_help(command: Command) -> bool {
@@ -227,8 +331,6 @@ pub fn update_help(command: Command) -> bool {
     }
 }
 
-const HELP_CONTENTS: &str = include_str!("../raws/help.txt");
-
 pub fn draw_help(ctx: &mut BTerm) {
     let x = 4;
     let y = 4;
@@ -237,7 +339,8 @@ pub fn draw_help(ctx: &mut BTerm) {
     ctx.draw_box(x, y, w, h, RGB::named(WHITE), RGB::named(BLACK));
     let mut builder = TextBuilder::empty();
     {
-        let stuff: Vec<&str> = HELP_CONTENTS.split('\n').collect();
+        let help = RAWS.lock().unwrap().help.clone();
+        let stuff: Vec<&str> = help.split('\n').collect();
         for line in stuff {
             builder.line_wrap(line).ln();
         }
@@ -255,16 +358,30 @@ pub struct MainMenuState {
     pub selection: i32,
     pub xpfile: XpFile,
     pub looking_at_help: bool,
+    /// a save file exists, so offer a third "Continue" entry
+    pub can_continue: bool,
+    /// typed in with the number keys, a new game is started with it
+    pub seed: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MainMenuRes {
+    NewGame,
+    Continue,
 }
 
-pub fn update_main_menu(mut ui_state: MainMenuState, command: Command) -> (bool, MainMenuState) {
+pub fn update_main_menu(
+    mut ui_state: MainMenuState,
+    command: Command,
+) -> (Option<MainMenuRes>, MainMenuState) {
     if ui_state.looking_at_help {
         let done = update_help(command);
         if done {
             ui_state.looking_at_help = false;
-            return (false, ui_state);
+            return (None, ui_state);
         }
     }
+    let entries = if ui_state.can_continue { 3 } else { 2 };
     match command {
         Command::Move {
             target: Point { x: 0, y: -1 },
@@ -276,21 +393,29 @@ pub fn update_main_menu(mut ui_state: MainMenuState, command: Command) -> (bool,
         Command::Move {
             target: Point { x: 0, y: 1 },
         } => {
-            if ui_state.selection + 1 < 2 {
+            if ui_state.selection + 1 < entries {
                 ui_state.selection += 1;
             };
         }
-        Command::Select => {
-            if ui_state.selection == 0 {
-                return (true, ui_state);
-            } else {
-                ui_state.looking_at_help = true;
-                return (false, ui_state);
+        Command::Number(n) => {
+            if ui_state.seed.len() < MAX_SEED_DIGITS {
+                ui_state.seed.push_str(&n.to_string());
             }
         }
+        Command::Erase => {
+            ui_state.seed.pop();
+        }
+        Command::Select => match ui_state.selection {
+            0 => return (Some(MainMenuRes::NewGame), ui_state),
+            1 => {
+                ui_state.looking_at_help = true;
+                return (None, ui_state);
+            }
+            _ => return (Some(MainMenuRes::Continue), ui_state),
+        },
         _ => {}
     }
-    return (false, ui_state);
+    return (None, ui_state);
 }
 
 pub fn draw_main_menu(ui_state: &MainMenuState, _state: &State, ctx: &mut BTerm) {
@@ -304,6 +429,22 @@ pub fn draw_main_menu(ui_state: &MainMenuState, _state: &State, ctx: &mut BTerm)
     let h = 62;
     ctx.draw_box(x, y, w, h, RGB::named(WHITE), RGB::named(BLACK));
     ctx.render_xp_sprite(&ui_state.xpfile, x + 1, y + 1);
+    if ui_state.can_continue {
+        ctx.print_color(
+            x + 1 + 31,
+            y + 1 + 20 + 2 * 2,
+            RGB::named(WHITE),
+            RGB::named(BLACK),
+            "Continue",
+        );
+    }
+    ctx.print_color(
+        x + 1 + 31,
+        y + 1 + 20 + 4 * 2,
+        RGB::named(GRAY),
+        RGB::named(BLACK),
+        format!("Seed: {}_", ui_state.seed),
+    );
     ctx.set(
         x + 1 + 29,
         y + 1 + 20 + ui_state.selection * 2,
@@ -365,28 +506,22 @@ pub fn draw_equip_examine(ui_state: &EquipExamineState, state: &State, ctx: &mut
             RGB::named(BLACK),
             to_cp437('☼'),
         );
-        let mut builder = TextBuilder::empty();
-        let ess = equip
-            .ingredients
-            .1
-            .iter()
-            .map(|x| Some(x.clone()))
-            .collect();
-        print_desc(equip.ingredients.0, &ess, &mut builder);
-        let mut block = TextBlock::new(
-            SIDEBAR_EXTRA_POS.x,
-            SIDEBAR_EXTRA_POS.y + 30,
-            LEFT_SIDEBAR_WIDTH - 2,
-            5,
-        );
-        block
-            .print(&builder)
-            .expect("Description text was too long");
-        let mut draw_batch = DrawBatch::new();
-        block.render_to_draw_batch(&mut draw_batch);
-        draw_batch.submit(0).unwrap();
-        render_draw_buffer(ctx).unwrap();
     }
+    let mut builder = TextBuilder::empty();
+    let ess: Vec<_> = equip
+        .ingredients
+        .1
+        .iter()
+        .map(|x| Some(x.clone()))
+        .collect();
+    print_desc(equip.ingredients.0, &ess, &mut builder);
+    draw_desc(
+        ctx,
+        &builder,
+        Point::new(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 30),
+        LEFT_SIDEBAR_WIDTH - 2,
+        EXAMINE_DESC_HEIGHT,
+    );
 }
 
 pub fn update_equip_examine(
@@ -675,6 +810,52 @@ pub fn draw_examine_ui(ui_state: &ExamineUIState, state: &State, ctx: &mut BTerm
                 if let Some(name) = query.get() {
                     ctx.print(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 1 + line, &name.0);
                     line += 1;
+                    if let Ok(mon) = state.ecs.get::<&Monster>(*entity) {
+                        let awareness = match mon.awareness {
+                            Awareness::Asleep => "asleep",
+                            Awareness::Idle => "unaware of you",
+                            Awareness::Searching => "searching",
+                            Awareness::Hunting => "hunting you",
+                        };
+                        ctx.print(
+                            SIDEBAR_EXTRA_POS.x,
+                            SIDEBAR_EXTRA_POS.y + 1 + line,
+                            format!(" ({})", awareness),
+                        );
+                        line += 1;
+                    }
+                    if let Ok(statuses) = state.ecs.get::<&StatusEffects>(*entity) {
+                        for status in statuses.0.iter() {
+                            ctx.print_color(
+                                SIDEBAR_EXTRA_POS.x,
+                                SIDEBAR_EXTRA_POS.y + 1 + line,
+                                RGB::named(WHITE),
+                                status.kind.color(),
+                                format!(" {} ({})", status.kind.name(), status.duration),
+                            );
+                            line += 1;
+                        }
+                    }
+                    if let Ok(resistances) = state.ecs.get::<&Resistances>(*entity) {
+                        let mut all: Vec<_> =
+                            resistances.0.iter().filter(|(_, p)| **p != 0).collect();
+                        all.sort_by_key(|(element, _)| element.to_string());
+                        for (element, percent) in all {
+                            let text = if *percent > 0 {
+                                format!(" Resists {}", element)
+                            } else {
+                                format!(" Weak to {}", element)
+                            };
+                            ctx.print_color(
+                                SIDEBAR_EXTRA_POS.x,
+                                SIDEBAR_EXTRA_POS.y + 1 + line,
+                                element_color(element),
+                                RGB::named(BLACK),
+                                text,
+                            );
+                            line += 1;
+                        }
+                    }
                     if state.debug {
                         let stuff =
                             debug::get_entity_components(state.ecs.entity(*entity).unwrap());
@@ -691,37 +872,17 @@ pub fn draw_examine_ui(ui_state: &ExamineUIState, state: &State, ctx: &mut BTerm
                     }
                 }
             }
-            match state.map.tiles[idx] {
-                map::Tile::Wall => {
-                    ctx.print(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 1 + line, "Wall");
-                }
-                map::Tile::Floor => {
-                    ctx.print(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 1 + line, "Floor");
-                }
-                map::Tile::Stairs => {
-                    ctx.print(
-                        SIDEBAR_EXTRA_POS.x,
-                        SIDEBAR_EXTRA_POS.y + 1 + line,
-                        "Stairs",
-                    );
-                }
-            }
+            ctx.print(
+                SIDEBAR_EXTRA_POS.x,
+                SIDEBAR_EXTRA_POS.y + 1 + line,
+                state.map.tiles[idx].info().name,
+            );
         } else if state.map.revealed_tiles[idx] {
-            match state.map.tiles[idx] {
-                map::Tile::Wall => {
-                    ctx.print(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 1 + line, "Wall");
-                }
-                map::Tile::Floor => {
-                    ctx.print(SIDEBAR_EXTRA_POS.x, SIDEBAR_EXTRA_POS.y + 1 + line, "Floor");
-                }
-                map::Tile::Stairs => {
-                    ctx.print(
-                        SIDEBAR_EXTRA_POS.x,
-                        SIDEBAR_EXTRA_POS.y + 1 + line,
-                        "Stairs",
-                    );
-                }
-            }
+            ctx.print(
+                SIDEBAR_EXTRA_POS.x,
+                SIDEBAR_EXTRA_POS.y + 1 + line,
+                state.map.tiles[idx].info().name,
+            );
         } else {
             ctx.print(
                 SIDEBAR_EXTRA_POS.x,
@@ -747,3 +908,61 @@ pub fn load_menus_xp() {
     link_resource!(RES_GAME_OVER, "../assets/game-over.xp");
     link_resource!(RES_YOU_WON, "../assets/you-won.xp");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{equipment::EquipmentType, essence::Essence};
+
+    /// Every way of filling every piece of equipment, part way or all the way
+    fn fillings(slots: usize) -> Vec<Vec<Option<Essence>>> {
+        let choices = [
+            None,
+            Some(Elemental::Fire),
+            Some(Elemental::Water),
+            Some(Elemental::Air),
+        ];
+        let mut all = vec![vec![]];
+        for _ in 0..slots {
+            all = all
+                .into_iter()
+                .flat_map(|filled: Vec<Option<Essence>>| {
+                    choices.iter().map(move |choice| {
+                        let mut filled = filled.clone();
+                        // the strongest essence has the longest numbers
+                        filled.push(choice.clone().map(|element| Essence { element, power: 2 }));
+                        filled
+                    })
+                })
+                .collect();
+        }
+        all
+    }
+
+    #[test]
+    fn descriptions_fit() {
+        RAWS.lock().unwrap().equipment =
+            serde_json::from_str(include_str!("../raws/equipment.json")).unwrap();
+        for (typ, slots) in [
+            (EquipmentType::Sword, 2),
+            (EquipmentType::Armor, 3),
+            (EquipmentType::Gun, 2),
+            (EquipmentType::Grapple, 1),
+        ] {
+            for filled in fillings(slots) {
+                let mut builder = TextBuilder::empty();
+                print_desc(typ, &filled, &mut builder);
+                assert!(
+                    wrapped_height(&builder, RIGHT_SIDEBAR_WIDTH - 1, BLUEPRINT_DESC_HEIGHT)
+                        .is_some(),
+                    "{typ:?} {filled:?}"
+                );
+                assert!(
+                    wrapped_height(&builder, LEFT_SIDEBAR_WIDTH - 2, EXAMINE_DESC_HEIGHT)
+                        .is_some(),
+                    "{typ:?} {filled:?}"
+                );
+            }
+        }
+    }
+}
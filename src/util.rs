@@ -3,10 +3,14 @@ use hecs::Entity;
 
 use crate::{
     components::{Position, Viewer},
+    status::{has_status, StatusKind},
     State,
 };
 
 pub fn push_entity_in_line_to(s: &mut State, e: Entity, dest: Point) {
+    if has_status(&s.ecs, e, StatusKind::KnockbackImmune) {
+        return;
+    }
     let player_pos = s.ecs.query_one_mut::<&Position>(s.player_entity).unwrap().0;
     let (target_pos, viewer) = s
         .ecs
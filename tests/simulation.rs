@@ -0,0 +1,398 @@
+use bracket_lib::prelude::*;
+use floyza_7drl_2023::{
+    boss::boss_act,
+    combat::{post, Event, HitKind},
+    components::*,
+    damage::Damage,
+    essence::Essence,
+    load_resources,
+    map::Tile,
+    mapping::Command,
+    replay::Replay,
+    replay_game,
+    save::{load_game, save_game},
+    systems::death::system_kill_dead,
+    GameOptions, OperatingMode, State,
+};
+use hecs::With;
+
+fn new_state(seed: u64) -> State {
+    load_resources();
+    let mut state = State::new_game(seed, GameOptions::default());
+    state.run_until_input();
+    state
+}
+
+fn teleport_player(state: &mut State, pt: Point) {
+    let (pos, viewer) = state
+        .ecs
+        .query_one_mut::<(&mut Position, &mut Viewer)>(state.player_entity)
+        .unwrap();
+    pos.0 = pt;
+    viewer.dirty = true;
+}
+
+fn logged(state: &State, prefix: &str) -> bool {
+    state.messages.log.iter().any(|m| m.starts_with(prefix))
+}
+
+#[test]
+fn same_seed_same_game() {
+    let a = new_state(1234);
+    let b = new_state(1234);
+    assert!(a.map.tiles == b.map.tiles);
+    assert_eq!(a.map.rooms, b.map.rooms);
+    assert_eq!(a.turn_order.len(), b.turn_order.len());
+}
+
+fn descend(state: &mut State) {
+    let stairs = state
+        .map
+        .tiles
+        .iter()
+        .position(|t| *t == Tile::Stairs)
+        .unwrap();
+    let stairs = state.map.index_to_point2d(stairs);
+    teleport_player(state, stairs);
+    state.step(Command::DescendStairs);
+}
+
+#[test]
+fn floors_only_depend_on_seed() {
+    let mut a = new_state(99);
+    let mut b = new_state(99);
+    for _ in 0..5 {
+        b.step(Command::Wait);
+    }
+    descend(&mut a);
+    descend(&mut b);
+    assert_eq!(a.map.depth, 1);
+    assert!(a.map.tiles == b.map.tiles);
+    assert_eq!(a.map.rooms, b.map.rooms);
+}
+
+#[test]
+fn floors_are_kept() {
+    let mut state = new_state(5);
+    let tiles = state.map.tiles.clone();
+    let items = state.ecs.query_mut::<With<&Position, &Item>>().into_iter().count();
+    descend(&mut state);
+    assert_eq!(state.map.depth, 1);
+    let arrival = state.map.start;
+    assert_eq!(state.map.tiles[state.map.point2d_to_index(arrival)], Tile::UpStairs);
+
+    state.step(Command::AscendStairs);
+    assert!(logged(&state, "You climb the stairs."));
+    assert_eq!(state.map.depth, 0);
+    assert!(state.map.tiles == tiles);
+    let restored = state.ecs.query_mut::<With<&Position, &Item>>().into_iter().count();
+    assert_eq!(restored, items);
+    let pos = state
+        .ecs
+        .query_one_mut::<&Position>(state.player_entity)
+        .unwrap()
+        .0;
+    assert_eq!(state.map.tiles[state.map.point2d_to_index(pos)], Tile::Stairs);
+
+    state.step(Command::DescendStairs);
+    assert_eq!(state.map.depth, 1);
+    let pos = state
+        .ecs
+        .query_one_mut::<&Position>(state.player_entity)
+        .unwrap()
+        .0;
+    assert_eq!(pos, arrival);
+}
+
+#[test]
+fn starts_waiting_for_input() {
+    let state = new_state(1);
+    assert_eq!(state.operating_mode, OperatingMode::WaitingForInput);
+    assert_eq!(state.map.depth, 0);
+}
+
+#[test]
+fn grab_forge_descend() {
+    let mut state = new_state(42);
+
+    // depth 0 always has an artifact in the starting room
+    let start_room = state.map.rooms[0];
+    let item_pos = state
+        .ecs
+        .query_mut::<With<&Position, &Item>>()
+        .into_iter()
+        .map(|(_, pos)| pos.0)
+        .find(|pt| start_room.point_in_rect(*pt))
+        .unwrap();
+    teleport_player(&mut state, item_pos);
+    state.step(Command::Grab);
+    assert!(logged(&state, "You pick up a"));
+
+    state.step(Command::OpenInventory);
+    state.step(Command::Select);
+    assert!(logged(&state, "You attach the"));
+    let player = state
+        .ecs
+        .query_one_mut::<&mut Player>(state.player_entity)
+        .unwrap();
+    player.current_blueprint.as_mut().unwrap().filled.push((
+        0,
+        Essence {
+            element: Elemental::Fire,
+            power: 0,
+        },
+    ));
+
+    state.step(Command::CreateItem);
+    assert!(logged(&state, "You forge a"));
+    let player = state
+        .ecs
+        .query_one_mut::<&Player>(state.player_entity)
+        .unwrap();
+    assert!(player.current_blueprint.is_none());
+    assert_eq!(
+        player.active_equipment.len() + player.passive_equipment.len(),
+        1
+    );
+
+    descend(&mut state);
+    assert!(logged(&state, "You descend the stairs."));
+    assert_eq!(state.map.depth, 1);
+}
+
+#[test]
+fn moving_into_a_wall_keeps_waiting() {
+    let mut state = new_state(7);
+    let idx = (0..state.map.tiles.len())
+        .find(|i| {
+            state.map.tiles[*i] == Tile::Floor && state.map.tiles[*i - 1] == Tile::Wall
+        })
+        .unwrap();
+    let pt = state.map.index_to_point2d(idx);
+    teleport_player(&mut state, pt);
+    let before = state.turn_order.clone();
+    state.step(Command::Move {
+        target: Point::new(-1, 0),
+    });
+    assert_eq!(state.operating_mode, OperatingMode::WaitingForInput);
+    assert_eq!(state.turn_order, before);
+    let pos = state
+        .ecs
+        .query_one_mut::<&Position>(state.player_entity)
+        .unwrap();
+    assert_eq!(pos.0, pt);
+}
+
+#[test]
+fn replay_reproduces_run() {
+    let mut commands = vec![];
+    for i in 0..40 {
+        let dir = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1)][i % 5];
+        commands.push(Command::Move {
+            target: Point::new(dir.0, dir.1),
+        });
+        commands.push(Command::Wait);
+    }
+    let mut played = new_state(2023);
+    for command in commands.iter() {
+        played.step(*command);
+    }
+
+    let mut replayed = replay_game(
+        Replay {
+            seed: 2023,
+            save: None,
+            commands,
+        },
+        GameOptions::default(),
+    );
+    while let Some(command) = replayed.playback.as_mut().unwrap().commands.pop_front() {
+        replayed.step(command);
+    }
+    assert_eq!(save_game(&played), save_game(&replayed));
+}
+
+#[test]
+fn replay_picks_up_from_a_save() {
+    let mut before = new_state(77);
+    for _ in 0..5 {
+        before.step(Command::Wait);
+    }
+    let save = save_game(&before);
+    let mut played = load_game(&save).unwrap();
+    let mut commands = vec![];
+    for i in 0..20 {
+        let dir = [(1, 0), (0, 1), (-1, 0), (0, -1)][i % 4];
+        commands.push(Command::Move {
+            target: Point::new(dir.0, dir.1),
+        });
+    }
+    for command in commands.iter() {
+        played.step(*command);
+    }
+
+    let mut replayed = replay_game(
+        Replay {
+            seed: 77,
+            save: Some(save),
+            commands,
+        },
+        GameOptions::default(),
+    );
+    while let Some(command) = replayed.playback.as_mut().unwrap().commands.pop_front() {
+        replayed.step(command);
+    }
+    assert_eq!(save_game(&played), save_game(&replayed));
+}
+
+#[test]
+fn bosses_warn_before_they_hit() {
+    let mut state = new_state(11);
+    for _ in 0..5 {
+        descend(&mut state);
+    }
+    assert_eq!(state.map.depth, 5);
+    let (fire, pos) = state
+        .ecs
+        .query_mut::<(&Name, &Position, &Boss)>()
+        .into_iter()
+        .find(|(_, (name, ..))| name.0 == "Incarnation of Fire")
+        .map(|(e, (_, pos, _))| (e, pos.0))
+        .unwrap();
+    let next_to = pos + Point::new(1, 0);
+    teleport_player(&mut state, next_to);
+    let hp = state
+        .ecs
+        .query_one_mut::<&Health>(state.player_entity)
+        .unwrap()
+        .hp;
+
+    assert!(boss_act(&mut state, fire, true).is_some());
+    assert!(logged(&state, "The Incarnation of Fire glows white hot!"));
+    let boss = state.ecs.query_one_mut::<&Boss>(fire).unwrap();
+    assert!(boss.telegraph.contains(&next_to));
+    let damage = boss.phases[0].damage;
+
+    assert!(boss_act(&mut state, fire, true).is_some());
+    assert!(logged(&state, "The blast burns you"));
+    assert!(state.ecs.query_one_mut::<&Boss>(fire).unwrap().telegraph.is_empty());
+    let health = state
+        .ecs
+        .query_one_mut::<&Health>(state.player_entity)
+        .unwrap();
+    assert_eq!(health.hp, hp - damage);
+}
+
+#[test]
+fn kills_are_announced_and_counted() {
+    let mut state = new_state(5);
+    let (monster, name) = state
+        .ecs
+        .query_mut::<With<(&Name, &mut Health), &Monster>>()
+        .into_iter()
+        .next()
+        .map(|(e, (name, health))| {
+            health.hp = 1;
+            (e, name.0.clone())
+        })
+        .unwrap();
+    post(
+        &mut state,
+        Event::Attack {
+            attacker: state.player_entity,
+            target: monster,
+        },
+    );
+    assert!(logged(&state, &format!("You hit the {}", name)));
+    assert!(state.stats.damage_dealt > 0);
+
+    system_kill_dead(&mut state);
+    assert!(!state.ecs.contains(monster));
+    assert_eq!(state.stats.kills, 1);
+
+    // burning to death isn't the player's doing
+    let other = state
+        .ecs
+        .query_mut::<With<&mut Health, &Monster>>()
+        .into_iter()
+        .next()
+        .map(|(e, health)| {
+            health.hp = 1;
+            e
+        })
+        .unwrap();
+    post(
+        &mut state,
+        Event::Hit {
+            source: None,
+            target: other,
+            damage: Damage::of(5, Elemental::Fire),
+            kind: HitKind::Burn,
+        },
+    );
+    system_kill_dead(&mut state);
+    assert!(!state.ecs.contains(other));
+    assert_eq!(state.stats.kills, 1);
+}
+
+#[test]
+fn shrugged_off_hits_do_no_damage() {
+    let mut state = new_state(5);
+    let monster = state
+        .ecs
+        .query_mut::<With<&Health, &Monster>>()
+        .into_iter()
+        .next()
+        .unwrap()
+        .0;
+    let resist = Resistances([(Elemental::Fire, 100)].into_iter().collect());
+    state.ecs.insert_one(monster, resist).unwrap();
+    post(
+        &mut state,
+        Event::Hit {
+            source: Some(state.player_entity),
+            target: monster,
+            damage: Damage::of(5, Elemental::Fire),
+            kind: HitKind::Melee,
+        },
+    );
+    assert!(logged(&state, "It resists!"));
+    assert!(!state.messages.log.iter().any(|m| m.contains("for 0 damage")));
+    assert_eq!(state.events.killer(monster), None);
+}
+
+#[test]
+fn armor_stops_at_nothing() {
+    let mut state = new_state(5);
+    let monster = state
+        .ecs
+        .query_mut::<With<&Attack, &Monster>>()
+        .into_iter()
+        .next()
+        .unwrap()
+        .0;
+    let player = state.player_entity;
+    state
+        .ecs
+        .insert_one(
+            player,
+            Defence {
+                armor: 100,
+                ..Defence::default()
+            },
+        )
+        .unwrap();
+    let hp = state.ecs.query_one_mut::<&Health>(player).unwrap().hp;
+
+    post(
+        &mut state,
+        Event::Attack {
+            attacker: monster,
+            target: player,
+        },
+    );
+    assert_eq!(state.ecs.query_one_mut::<&Health>(player).unwrap().hp, hp);
+    assert_eq!(state.stats.damage_taken, 0);
+    assert!(logged(&state, "You block the"));
+    assert!(!state.messages.log.iter().any(|m| m.contains("for 0 damage")));
+}